@@ -23,6 +23,28 @@ enum SubTable {
     },
 }
 
+/// SDD node tables which have been detached from an `SddTable` by
+/// `SddTable::reorder`, indexed by their vtree index before the reordering.
+/// Their nodes remain readable so that they can be rebuilt under the new vtree.
+pub struct DetachedSddNodes {
+    tables: Vec<Option<BackedRobinHoodTable<Vec<(SddPtr, SddPtr)>>>>,
+}
+
+impl DetachedSddNodes {
+    /// true if the nodes at (old) vtree index `vnode` were detached
+    pub fn contains(&self, vnode: usize) -> bool {
+        self.tables[vnode].is_some()
+    }
+
+    /// Fetch the slice for a set of or-nodes; panics if `ptr` was not detached
+    pub fn sdd_get_or(&self, ptr: SddPtr) -> &[(SddPtr, SddPtr)] {
+        match &self.tables[ptr.vtree()] {
+            &Some(ref tbl) => &tbl.deref(BackingPtr(ptr.idx() as u32)),
+            &None => panic!("dereferencing a node which was not detached"),
+        }
+    }
+}
+
 /// Handles memory management for the SDD manager
 pub struct SddTable {
     /// mapping between sdd and bdd variable labels
@@ -96,6 +118,36 @@ impl SddTable {
         }
     }
 
+    /// Rearranges the subtables to follow a new vtree. `new_to_old[i]` is the
+    /// vtree index, before the change, of the node now at index `i`. The SDD
+    /// subtables of the (old) nodes marked in `rebuild` are replaced by empty
+    /// tables and returned so that their nodes can be rebuilt; every other
+    /// subtable, including all BDD subtables, is moved over unchanged.
+    pub fn reorder(&mut self, new_to_old: &[usize], rebuild: &[bool]) -> DetachedSddNodes {
+        let mut old: Vec<Option<SubTable>> = self.tables.drain(..).map(Some).collect();
+        let mut detached = Vec::with_capacity(old.len());
+        for (idx, t) in old.iter_mut().enumerate() {
+            if rebuild[idx] {
+                match t.take() {
+                    Some(SubTable::SddSubTable { tbl }) => detached.push(Some(tbl)),
+                    _ => panic!("rebuilding a BDD subtable"),
+                }
+            } else {
+                detached.push(None);
+            }
+        }
+        for &old_idx in new_to_old.iter() {
+            let t = match old[old_idx].take() {
+                Some(t) => t,
+                None => SubTable::SddSubTable {
+                    tbl: BackedRobinHoodTable::new(DEFAULT_RH_SZ),
+                },
+            };
+            self.tables.push(t);
+        }
+        DetachedSddNodes { tables: detached }
+    }
+
     /// Fetch the slice for a set of or-nodes; panics if this is not an SDD node
     pub fn sdd_get_or(&self, ptr: SddPtr) -> &[(SddPtr, SddPtr)] {
         match &self.tables[ptr.vtree() as usize] {
@@ -0,0 +1,158 @@
+//! A CNF compiler which supports adding and removing clauses.
+//!
+//! The clauses are the leaves of a complete binary tree whose internal nodes
+//! hold the conjunction of their children, so a change recomputes only the
+//! conjunctions on the path from its leaf to the root.
+
+use super::Target;
+use repr::var_label::Literal;
+
+/// Identifies a clause added to an `Incremental` compiler
+pub type ClauseId = usize;
+
+pub struct Incremental<M: Target> {
+    man: M,
+    /// the clause in each leaf of the tree, or None if the leaf is free
+    clauses: Vec<Option<Vec<Literal>>>,
+    /// the tree, stored as a heap: node `i` has children `2i` and `2i + 1`,
+    /// the root is node 1, and leaf `j` is node `clauses.len() + j`
+    tree: Vec<M::Ptr>,
+    free: Vec<ClauseId>,
+}
+
+impl<M: Target> Incremental<M> {
+    /// Creates a compiler with no clauses, whose root is true
+    pub fn new(mut man: M) -> Incremental<M> {
+        let t = man.true_ptr();
+        Incremental {
+            man: man,
+            clauses: vec![None],
+            tree: vec![t, t],
+            free: vec![0],
+        }
+    }
+
+    pub fn manager(&self) -> &M {
+        &self.man
+    }
+
+    pub fn manager_mut(&mut self) -> &mut M {
+        &mut self.man
+    }
+
+    pub fn into_manager(self) -> M {
+        self.man
+    }
+
+    /// The conjunction of the current clauses
+    pub fn root(&self) -> M::Ptr {
+        self.tree[1]
+    }
+
+    /// The current clauses along with their ids
+    pub fn clauses(&self) -> Vec<(ClauseId, &[Literal])> {
+        self.clauses
+            .iter()
+            .enumerate()
+            .filter_map(|(i, c)| c.as_ref().map(|c| (i, &c[..])))
+            .collect()
+    }
+
+    /// Recomputes the leaf `id` and its ancestors
+    fn update(&mut self, id: ClauseId) {
+        let mut node = self.clauses.len() + id;
+        self.tree[node] = match self.clauses[id] {
+            Some(ref c) => self.man.clause(c),
+            None => self.man.true_ptr(),
+        };
+        while node > 1 {
+            node /= 2;
+            self.tree[node] = self.man.conj(self.tree[2 * node], self.tree[2 * node + 1]);
+        }
+    }
+
+    /// Doubles the number of leaves, moving each clause to the same position
+    /// in the new leaves and rebuilding the tree
+    fn grow(&mut self) {
+        let old = self.clauses.len();
+        self.clauses.extend((0..old).map(|_| None));
+        self.free.extend((old..2 * old).rev());
+        let t = self.man.true_ptr();
+        let mut tree = vec![t; 4 * old];
+        tree[2 * old..3 * old].copy_from_slice(&self.tree[old..2 * old]);
+        for node in (1..2 * old).rev() {
+            tree[node] = self.man.conj(tree[2 * node], tree[2 * node + 1]);
+        }
+        self.tree = tree;
+    }
+
+    /// Conjoins `clause`, returning its id and the new root
+    pub fn add_clause(&mut self, clause: &[Literal]) -> (ClauseId, M::Ptr) {
+        if self.free.is_empty() {
+            self.grow();
+        }
+        let id = self.free.pop().unwrap();
+        self.clauses[id] = Some(clause.to_vec());
+        self.update(id);
+        (id, self.root())
+    }
+
+    /// Removes the clause `id`, returning the new root; panics if there is no
+    /// such clause
+    pub fn remove_clause(&mut self, id: ClauseId) -> M::Ptr {
+        assert!(
+            id < self.clauses.len() && self.clauses[id].is_some(),
+            "no clause with id {}",
+            id
+        );
+        self.clauses[id] = None;
+        self.free.push(id);
+        self.update(id);
+        self.root()
+    }
+}
+
+#[cfg(test)]
+mod test_incremental {
+    use super::*;
+    use manager::rsbdd_manager::BddManager;
+    use manager::sdd_manager::{even_split, SddManager};
+    use repr::cnf::Cnf;
+    use repr::var_label::VarLabel;
+
+    quickcheck! {
+        fn add_remove_bdd(c: Cnf) -> bool {
+            let mut inc = Incremental::new(BddManager::new_default_order(16));
+            let ids: Vec<ClauseId> = c.clauses().iter().map(|cl| inc.add_clause(cl).0).collect();
+            let all = inc.root();
+            // remove every other clause, then add them back
+            let mut kept = Vec::new();
+            for (i, id) in ids.iter().enumerate() {
+                if i % 2 == 0 {
+                    inc.remove_clause(*id);
+                } else {
+                    kept.push(c.clauses()[i].clone());
+                }
+            }
+            let some = inc.root();
+            let readded: Vec<ClauseId> = c.clauses().iter().step_by(2).map(|cl| inc.add_clause(cl).0).collect();
+            let again = inc.root();
+            let expected_all = inc.manager_mut().from_cnf(&c);
+            let expected_some = inc.manager_mut().from_cnf(&Cnf::new(kept));
+            all == expected_all && some == expected_some && again == expected_all
+                && inc.clauses().len() == c.clauses().len()
+                && readded.iter().all(|id| ids.contains(id))
+        }
+    }
+
+    quickcheck! {
+        fn add_remove_sdd(c: Cnf) -> bool {
+            let order: Vec<VarLabel> = (0..16).map(|v| VarLabel::new(v)).collect();
+            let mut inc = Incremental::new(SddManager::new(even_split(&order, 3)));
+            let ids: Vec<ClauseId> = c.clauses().iter().map(|cl| inc.add_clause(cl).0).collect();
+            let root = ids.iter().skip(1).fold(inc.root(), |_, id| inc.remove_clause(*id));
+            let expected = inc.manager_mut().from_cnf(&Cnf::new(c.clauses().iter().take(1).cloned().collect()));
+            inc.manager().sdd_eq(root, expected)
+        }
+    }
+}
@@ -0,0 +1,106 @@
+//! Compiling CNFs into decision diagrams
+
+use manager::rsbdd_manager::BddManager;
+use manager::sdd_manager::SddManager;
+use repr::bdd::BddPtr;
+use repr::sdd::SddPtr;
+use repr::var_label::{Literal, VarLabel};
+use util::btree::BTree;
+
+pub mod incremental;
+pub mod pb;
+pub mod preprocess;
+pub mod schedule;
+pub mod top_down;
+
+/// A manager which the compilers in this module can build diagrams in
+pub trait Target {
+    type Ptr: Copy;
+    fn true_ptr(&mut self) -> Self::Ptr;
+    fn false_ptr(&mut self) -> Self::Ptr;
+    fn lit(&mut self, l: Literal) -> Self::Ptr;
+    fn conj(&mut self, a: Self::Ptr, b: Self::Ptr) -> Self::Ptr;
+    fn disj(&mut self, a: Self::Ptr, b: Self::Ptr) -> Self::Ptr;
+    /// `if v then high else low`, where neither branch mentions `v` or any
+    /// variable before it in `var_order`
+    fn decide(&mut self, v: VarLabel, low: Self::Ptr, high: Self::Ptr) -> Self::Ptr;
+    /// the number of nodes in `p`
+    fn size(&self, p: Self::Ptr) -> usize;
+    /// the manager's variables, first to last
+    fn var_order(&self) -> Vec<VarLabel>;
+
+    fn clause(&mut self, c: &[Literal]) -> Self::Ptr {
+        let mut r = self.false_ptr();
+        for l in c.iter() {
+            let v = self.lit(*l);
+            r = self.disj(r, v);
+        }
+        r
+    }
+}
+
+impl Target for BddManager {
+    type Ptr = BddPtr;
+    fn true_ptr(&mut self) -> BddPtr {
+        BddManager::true_ptr(self)
+    }
+    fn false_ptr(&mut self) -> BddPtr {
+        BddManager::false_ptr(self)
+    }
+    fn lit(&mut self, l: Literal) -> BddPtr {
+        self.var(l.get_label(), l.get_polarity())
+    }
+    fn conj(&mut self, a: BddPtr, b: BddPtr) -> BddPtr {
+        self.and(a, b)
+    }
+    fn disj(&mut self, a: BddPtr, b: BddPtr) -> BddPtr {
+        self.or(a, b)
+    }
+    fn decide(&mut self, v: VarLabel, low: BddPtr, high: BddPtr) -> BddPtr {
+        self.mk_node(v, low, high)
+    }
+    fn size(&self, p: BddPtr) -> usize {
+        self.count_nodes(p)
+    }
+    fn var_order(&self) -> Vec<VarLabel> {
+        let order = self.get_order();
+        (0..order.len()).map(|i| order.var_at_pos(i)).collect()
+    }
+}
+
+impl Target for SddManager {
+    type Ptr = SddPtr;
+    fn true_ptr(&mut self) -> SddPtr {
+        SddPtr::new_const(true)
+    }
+    fn false_ptr(&mut self) -> SddPtr {
+        SddPtr::new_const(false)
+    }
+    fn lit(&mut self, l: Literal) -> SddPtr {
+        self.var(l.get_label(), l.get_polarity())
+    }
+    fn conj(&mut self, a: SddPtr, b: SddPtr) -> SddPtr {
+        self.and(a, b)
+    }
+    fn disj(&mut self, a: SddPtr, b: SddPtr) -> SddPtr {
+        self.or(a, b)
+    }
+    fn decide(&mut self, v: VarLabel, low: SddPtr, high: SddPtr) -> SddPtr {
+        let v = self.var(v, true);
+        self.ite(v, high, low)
+    }
+    fn size(&self, p: SddPtr) -> usize {
+        self.count_nodes(p) as usize
+    }
+    /// the variables of the vtree's leaves, left to right
+    fn var_order(&self) -> Vec<VarLabel> {
+        self.get_vtree_root()
+            .in_order_iter()
+            .filter_map(|n| match n {
+                &BTree::Leaf(ref vars) => Some(vars.iter().cloned()),
+                &BTree::Node(..) => None,
+            })
+            .flat_map(|vars| vars)
+            .collect()
+    }
+}
@@ -0,0 +1,82 @@
+//! Compiles pseudo-Boolean constraints by layered dynamic programming.
+//!
+//! After normalizing to `w_1 l_1 + ... + w_n l_n <= k` with positive weights
+//! and the literals sorted by the target's variable order, the constraint is
+//! built one variable per layer: the node for layer `i` with remaining budget
+//! `k` decides `l_i` and continues to layer `i + 1` with budget `k` or
+//! `k - w_i`. Nodes are shared between equal (layer, budget) pairs, and a
+//! budget which is negative or covers every remaining weight ends the layer.
+
+use super::Target;
+use repr::pb::{Opb, PbConstraint, PbOp};
+use repr::var_label::{Literal, VarLabel};
+use std::collections::HashMap;
+
+fn compile_le<M: Target>(man: &mut M, c: &PbConstraint, ge: bool) -> M::Ptr {
+    let (mut terms, k) = c.normalize_le(ge);
+    let position: HashMap<VarLabel, usize> = man
+        .var_order()
+        .into_iter()
+        .enumerate()
+        .map(|(i, v)| (v, i))
+        .collect();
+    terms.sort_by_key(|&(l, _)| position[&l.get_label()]);
+    // the sum of the weights from each layer on
+    let mut rest = vec![0; terms.len() + 1];
+    for i in (0..terms.len()).rev() {
+        rest[i] = rest[i + 1] + terms[i].1;
+    }
+
+    fn layer<M: Target>(
+        man: &mut M,
+        terms: &[(Literal, u64)],
+        rest: &[u64],
+        memo: &mut HashMap<(usize, i64), M::Ptr>,
+        i: usize,
+        k: i64,
+    ) -> M::Ptr {
+        if k < 0 {
+            return man.false_ptr();
+        }
+        if k as u64 >= rest[i] {
+            return man.true_ptr();
+        }
+        if let Some(r) = memo.get(&(i, k)) {
+            return *r;
+        }
+        let (l, w) = terms[i];
+        let with = layer(man, terms, rest, memo, i + 1, k - w as i64);
+        let without = layer(man, terms, rest, memo, i + 1, k);
+        let r = if l.get_polarity() {
+            man.decide(l.get_label(), without, with)
+        } else {
+            man.decide(l.get_label(), with, without)
+        };
+        memo.insert((i, k), r);
+        r
+    }
+    layer(man, &terms, &rest, &mut HashMap::new(), 0, k)
+}
+
+/// Builds the function true exactly when `c` holds
+pub fn compile_pb<M: Target>(man: &mut M, c: &PbConstraint) -> M::Ptr {
+    match c.op {
+        PbOp::Le => compile_le(man, c, false),
+        PbOp::Ge => compile_le(man, c, true),
+        PbOp::Eq => {
+            let le = compile_le(man, c, false);
+            let ge = compile_le(man, c, true);
+            man.conj(le, ge)
+        }
+    }
+}
+
+/// Builds the conjunction of the constraints of `opb`
+pub fn compile_opb<M: Target>(man: &mut M, opb: &Opb) -> M::Ptr {
+    let mut r = man.true_ptr();
+    for c in opb.constraints.iter() {
+        let f = compile_pb(man, c);
+        r = man.conj(r, f);
+    }
+    r
+}
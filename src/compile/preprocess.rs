@@ -0,0 +1,388 @@
+//! Simplifies a `Cnf` before it is compiled.
+//!
+//! Unit propagation, tautology and duplicate-literal removal, and subsumption
+//! preserve the CNF's models (once the implied literals are conjoined back
+//! in). Pure-literal elimination and bounded variable elimination existentially
+//! quantify variables away; they only apply to *eliminable* variables, which
+//! are all variables unless `preserve_count` is set, in which case they are the
+//! variables outside of the projection set.
+
+use repr::cnf::Cnf;
+use repr::var_label::{Literal, VarLabel};
+use std::collections::{HashMap, HashSet};
+
+/// The steps performed by `preprocess`
+#[derive(Debug, Clone)]
+pub struct PreprocessOptions {
+    pub unit_propagation: bool,
+    pub subsumption: bool,
+    pub pure_literals: bool,
+    /// eliminate variables by resolution when this does not increase the
+    /// number of clauses
+    pub variable_elimination: bool,
+    /// if set, the preprocessed CNF (with the implied literals) has the same
+    /// models as the original CNF when projected onto `projection`
+    pub preserve_count: bool,
+    /// the variables whose models are counted; None means every variable
+    pub projection: Option<Vec<VarLabel>>,
+}
+
+impl PreprocessOptions {
+    /// Performs every step, without preserving model counts
+    pub fn new() -> PreprocessOptions {
+        PreprocessOptions::default()
+    }
+
+    /// Performs every step which preserves the model count projected onto
+    /// `projection` (or onto every variable, if it is None)
+    pub fn counting(projection: Option<Vec<VarLabel>>) -> PreprocessOptions {
+        PreprocessOptions {
+            preserve_count: true,
+            projection: projection,
+            ..PreprocessOptions::new()
+        }
+    }
+}
+
+impl Default for PreprocessOptions {
+    fn default() -> PreprocessOptions {
+        PreprocessOptions {
+            unit_propagation: true,
+            subsumption: true,
+            pure_literals: true,
+            variable_elimination: true,
+            preserve_count: false,
+            projection: None,
+        }
+    }
+}
+
+/// The result of `preprocess`
+#[derive(Debug, Clone)]
+pub struct Preprocessed {
+    /// the simplified CNF, which mentions none of the implied or eliminated
+    /// variables; if the original CNF is unsatisfiable this is the
+    /// contradiction `x0 && !x0`
+    pub cnf: Cnf,
+    /// literals implied by the original CNF
+    pub implied: Vec<Literal>,
+    /// the variables removed by pure-literal or variable elimination
+    pub eliminated: Vec<VarLabel>,
+    /// true if a conflict was found, so the original CNF is unsatisfiable
+    pub unsat: bool,
+}
+
+fn negate(l: Literal) -> Literal {
+    Literal::new(l.get_label(), !l.get_polarity())
+}
+
+/// Removes duplicate literals from `clause`, returning None if it is a
+/// tautology
+fn normalize(clause: &[Literal]) -> Option<Vec<Literal>> {
+    let mut r: Vec<Literal> = Vec::with_capacity(clause.len());
+    for l in clause.iter() {
+        if r.contains(&negate(*l)) {
+            return None;
+        }
+        if !r.contains(l) {
+            r.push(*l);
+        }
+    }
+    Some(r)
+}
+
+/// true if every literal of `a` is in `b`
+fn subsumes(a: &[Literal], b: &[Literal]) -> bool {
+    a.len() <= b.len() && a.iter().all(|l| b.contains(l))
+}
+
+struct State {
+    clauses: Vec<Vec<Literal>>,
+    implied: Vec<Literal>,
+    eliminated: Vec<VarLabel>,
+    unsat: bool,
+}
+
+impl State {
+    /// Propagates unit clauses until none are left; returns true if any were
+    /// found
+    fn propagate(&mut self) -> bool {
+        let mut changed = false;
+        loop {
+            let unit = match self.clauses.iter().find(|c| c.len() == 1) {
+                Some(c) => c[0],
+                None => return changed,
+            };
+            changed = true;
+            self.implied.push(unit);
+            let neg = negate(unit);
+            let mut clauses = Vec::with_capacity(self.clauses.len());
+            for c in self.clauses.drain(..) {
+                if c.contains(&unit) {
+                    continue;
+                }
+                let c: Vec<Literal> = c.into_iter().filter(|l| *l != neg).collect();
+                if c.is_empty() {
+                    self.unsat = true;
+                    return true;
+                }
+                clauses.push(c);
+            }
+            self.clauses = clauses;
+        }
+    }
+
+    /// Removes every clause which is subsumed by another; returns true if any
+    /// were removed
+    fn subsume(&mut self) -> bool {
+        let mut clauses = self.clauses.clone();
+        clauses.sort_by_key(|c| c.len());
+        let mut kept: Vec<Vec<Literal>> = Vec::with_capacity(clauses.len());
+        for c in clauses.into_iter() {
+            if !kept.iter().any(|k| subsumes(k, &c)) {
+                kept.push(c);
+            }
+        }
+        let changed = kept.len() != self.clauses.len();
+        self.clauses = kept;
+        changed
+    }
+
+    /// The clauses containing each literal
+    fn occurrences(&self) -> HashMap<Literal, Vec<usize>> {
+        let mut occ: HashMap<Literal, Vec<usize>> = HashMap::new();
+        for (i, c) in self.clauses.iter().enumerate() {
+            for l in c.iter() {
+                occ.entry(*l).or_insert_with(Vec::new).push(i);
+            }
+        }
+        occ
+    }
+
+    /// Removes the clauses containing a pure literal of an eliminable
+    /// variable; returns true if any were found
+    fn pure_literals(&mut self, eliminable: &dyn Fn(VarLabel) -> bool) -> bool {
+        let occ = self.occurrences();
+        let mut pure: Vec<Literal> = occ
+            .keys()
+            .filter(|l| eliminable(l.get_label()) && !occ.contains_key(&negate(**l)))
+            .cloned()
+            .collect();
+        if pure.is_empty() {
+            return false;
+        }
+        pure.sort_by_key(|l| l.get_label().value());
+        self.eliminated.extend(pure.iter().map(|l| l.get_label()));
+        self.clauses.retain(|c| !c.iter().any(|l| pure.contains(l)));
+        true
+    }
+
+    /// Eliminates the first eliminable variable whose resolvents are no more
+    /// numerous than the clauses they replace; returns true if one was found
+    fn eliminate_variable(&mut self, eliminable: &dyn Fn(VarLabel) -> bool) -> bool {
+        let occ = self.occurrences();
+        let mut vars: Vec<VarLabel> = occ
+            .keys()
+            .map(|l| l.get_label())
+            .filter(|v| eliminable(*v))
+            .collect();
+        vars.sort_by_key(|v| v.value());
+        vars.dedup();
+        for v in vars.into_iter() {
+            let empty = Vec::new();
+            let pos = occ.get(&Literal::new(v, true)).unwrap_or(&empty);
+            let neg = occ.get(&Literal::new(v, false)).unwrap_or(&empty);
+            let mut resolvents: Vec<Vec<Literal>> = Vec::new();
+            let mut bounded = true;
+            'outer: for p in pos.iter() {
+                for n in neg.iter() {
+                    let mut r: Vec<Literal> = self.clauses[*p]
+                        .iter()
+                        .chain(self.clauses[*n].iter())
+                        .filter(|l| l.get_label() != v)
+                        .cloned()
+                        .collect();
+                    r = match normalize(&r) {
+                        Some(r) => r,
+                        None => continue,
+                    };
+                    resolvents.push(r);
+                    if resolvents.len() > pos.len() + neg.len() {
+                        bounded = false;
+                        break 'outer;
+                    }
+                }
+            }
+            if !bounded {
+                continue;
+            }
+            let removed: HashSet<usize> = pos.iter().chain(neg.iter()).cloned().collect();
+            let mut clauses: Vec<Vec<Literal>> = self
+                .clauses
+                .iter()
+                .enumerate()
+                .filter(|&(i, _)| !removed.contains(&i))
+                .map(|(_, c)| c.clone())
+                .collect();
+            for r in resolvents.into_iter() {
+                if r.is_empty() {
+                    self.unsat = true;
+                }
+                clauses.push(r);
+            }
+            self.clauses = clauses;
+            self.eliminated.push(v);
+            return true;
+        }
+        false
+    }
+}
+
+/// Simplifies `cnf` by the steps enabled in `opts`, repeating them until none
+/// applies
+pub fn preprocess(cnf: &Cnf, opts: &PreprocessOptions) -> Preprocessed {
+    let projection: Option<HashSet<VarLabel>> = opts
+        .projection
+        .as_ref()
+        .map(|p| p.iter().cloned().collect());
+    let preserve_count = opts.preserve_count;
+    let eliminable = |v: VarLabel| match projection {
+        _ if !preserve_count => true,
+        Some(ref p) => !p.contains(&v),
+        None => false,
+    };
+
+    let mut s = State {
+        clauses: cnf.clauses().iter().filter_map(|c| normalize(c)).collect(),
+        implied: Vec::new(),
+        eliminated: Vec::new(),
+        unsat: false,
+    };
+    // an empty clause is a conflict before any step runs
+    s.unsat = s.clauses.iter().any(Vec::is_empty);
+    loop {
+        let mut changed = false;
+        if !s.unsat && opts.unit_propagation {
+            changed |= s.propagate();
+        }
+        if !s.unsat && opts.subsumption {
+            changed |= s.subsume();
+        }
+        if !s.unsat && opts.pure_literals {
+            changed |= s.pure_literals(&eliminable);
+        }
+        if !s.unsat && opts.variable_elimination {
+            changed |= s.eliminate_variable(&eliminable);
+        }
+        if s.unsat || !changed {
+            break;
+        }
+    }
+
+    let cnf = if s.unsat {
+        let x0 = Literal::new(VarLabel::new(0), true);
+        Cnf::new(vec![vec![x0], vec![negate(x0)]])
+    } else {
+        Cnf::new(s.clauses)
+    };
+    Preprocessed {
+        cnf: cnf,
+        implied: s.implied,
+        eliminated: s.eliminated,
+        unsat: s.unsat,
+    }
+}
+
+#[cfg(test)]
+mod test_preprocess {
+    use super::*;
+    use manager::rsbdd_manager::{BddManager, BddWmc};
+    use repr::bdd::BddPtr;
+
+    /// the BDD of the preprocessed CNF conjoined with its implied literals
+    fn compile(man: &mut BddManager, p: &Preprocessed) -> BddPtr {
+        let f = if p.unsat {
+            man.false_ptr()
+        } else {
+            man.from_cnf(&p.cnf)
+        };
+        p.implied.iter().fold(f, |acc, l| {
+            let v = man.var(l.get_label(), l.get_polarity());
+            man.and(acc, v)
+        })
+    }
+
+    fn count(man: &BddManager, f: BddPtr) -> usize {
+        let weights = (0..16).map(|v| (VarLabel::new(v), (1, 1))).collect();
+        man.wmc(f, &BddWmc::new_with_default(0, 1, weights))
+    }
+
+    #[test]
+    fn simple_steps() {
+        let lit = |v: u64, p: bool| Literal::new(VarLabel::new(v), p);
+        // x0 is a unit, (x1 || x2) subsumes (x1 || x2 || x3), (x4 || !x4) is a
+        // tautology, and x5 is pure
+        let cnf = Cnf::new(vec![
+            vec![lit(0, true)],
+            vec![lit(0, false), lit(1, true), lit(2, true)],
+            vec![lit(1, true), lit(2, true), lit(3, true), lit(1, true)],
+            vec![lit(4, true), lit(4, false)],
+            vec![lit(5, true), lit(3, false)],
+        ]);
+        let mut opts = PreprocessOptions::counting(None);
+        let p = preprocess(&cnf, &opts);
+        assert_eq!(p.implied, vec![lit(0, true)]);
+        assert_eq!(
+            p.cnf.clauses(),
+            &[
+                vec![lit(1, true), lit(2, true)],
+                vec![lit(5, true), lit(3, false)]
+            ][..]
+        );
+        opts.preserve_count = false;
+        let p = preprocess(&cnf, &opts);
+        // every remaining literal is pure
+        let eliminated: Vec<u64> = p.eliminated.iter().map(|v| v.value()).collect();
+        assert_eq!(eliminated, vec![1, 2, 3, 5]);
+        assert!(p.cnf.clauses().is_empty() && !p.unsat);
+    }
+
+    quickcheck! {
+        fn preserves_count(c: Cnf) -> bool {
+            let mut man = BddManager::new_default_order(16);
+            let f = man.from_cnf(&c);
+            let p = preprocess(&c, &PreprocessOptions::counting(None));
+            let g = compile(&mut man, &p);
+            f == g && p.eliminated.is_empty()
+        }
+    }
+
+    quickcheck! {
+        fn preserves_projected_count(c: Cnf) -> bool {
+            let mut man = BddManager::new_default_order(16);
+            // project away the odd variables
+            let shown = (0..16).filter(|v| v % 2 == 0).map(VarLabel::new).collect();
+            let odd: Vec<VarLabel> = (0..16).filter(|v| v % 2 == 1).map(VarLabel::new).collect();
+            let f = man.from_cnf(&c);
+            let f = odd.iter().fold(f, |acc, v| man.exists(acc, *v));
+            let p = preprocess(&c, &PreprocessOptions::counting(Some(shown)));
+            let g = compile(&mut man, &p);
+            let g = odd.iter().fold(g, |acc, v| man.exists(acc, *v));
+            f == g && count(&man, f) == count(&man, g)
+        }
+    }
+
+    quickcheck! {
+        fn preserves_satisfiability(c: Cnf) -> bool {
+            let mut man = BddManager::new_default_order(16);
+            let f = man.from_cnf(&c);
+            let p = preprocess(&c, &PreprocessOptions::new());
+            let g = compile(&mut man, &p);
+            // every model of the result is a model of the original CNF once the
+            // eliminated variables are forgotten
+            let f_proj = p.eliminated.iter().fold(f, |acc, v| man.exists(acc, *v));
+            let g_proj = p.eliminated.iter().fold(g, |acc, v| man.exists(acc, *v));
+            f.is_false() == g.is_false() && f_proj == g_proj
+        }
+    }
+}
@@ -0,0 +1,273 @@
+//! Bottom-up compilation of a CNF with a choice of the order in which its
+//! clauses are conjoined.
+
+use super::Target;
+use manager::rsbdd_manager::BddManager;
+use manager::sdd_manager::{right_linear, SddManager};
+use repr::bdd::BddPtr;
+use repr::cnf::Cnf;
+use repr::sdd::{SddPtr, VTree};
+use repr::var_label::{Literal, VarLabel};
+use std::collections::{HashMap, HashSet};
+use util::btree::BTree;
+
+/// The order in which the clauses of a CNF are conjoined
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Schedule {
+    /// one clause at a time, in the order they appear in the CNF
+    Linear,
+    /// pairwise, in a balanced binary tree
+    Balanced,
+    /// bucket elimination along the variable order: each clause is placed in
+    /// the bucket of its last variable, and buckets are conjoined from the
+    /// last variable to the first, each result moving to the bucket of the
+    /// last variable it shares with earlier buckets
+    Buckets,
+    /// each clause is conjoined at the deepest vtree node containing all of
+    /// its variables, and the vtree is compiled bottom-up; for BDDs the vtree
+    /// is the right-linear one of the variable order
+    VTree,
+}
+
+#[derive(Debug, Clone)]
+pub struct ScheduleOptions {
+    pub schedule: Schedule,
+    /// record the size of the result of every conjunction
+    pub record_sizes: bool,
+}
+
+impl ScheduleOptions {
+    pub fn new() -> ScheduleOptions {
+        ScheduleOptions::default()
+    }
+}
+
+impl Default for ScheduleOptions {
+    fn default() -> ScheduleOptions {
+        ScheduleOptions {
+            schedule: Schedule::Buckets,
+            record_sizes: false,
+        }
+    }
+}
+
+struct Run<'a, M: Target + 'a> {
+    man: &'a mut M,
+    sizes: Option<Vec<usize>>,
+}
+
+impl<'a, M: Target> Run<'a, M> {
+    fn and(&mut self, a: M::Ptr, b: M::Ptr) -> M::Ptr {
+        let r = self.man.conj(a, b);
+        if let Some(ref mut sizes) = self.sizes {
+            sizes.push(self.man.size(r));
+        }
+        r
+    }
+
+    fn and_linear(&mut self, v: &[M::Ptr]) -> M::Ptr {
+        match v.split_first() {
+            None => self.man.true_ptr(),
+            Some((first, rest)) => rest.iter().fold(*first, |acc, f| self.and(acc, *f)),
+        }
+    }
+
+    fn and_balanced(&mut self, v: &[M::Ptr]) -> M::Ptr {
+        match v.len() {
+            0 => self.man.true_ptr(),
+            1 => v[0],
+            _ => {
+                let (l, r) = v.split_at(v.len() / 2);
+                let l = self.and_balanced(l);
+                let r = self.and_balanced(r);
+                self.and(l, r)
+            }
+        }
+    }
+
+    fn buckets(&mut self, clauses: &[Vec<Literal>]) -> M::Ptr {
+        let order = self.man.var_order();
+        let position: HashMap<VarLabel, usize> =
+            order.iter().enumerate().map(|(i, v)| (*v, i)).collect();
+        // each bucket holds functions along with the positions of the
+        // variables they mention
+        let mut buckets: Vec<Vec<(M::Ptr, HashSet<usize>)>> = vec![Vec::new(); order.len()];
+        for c in clauses.iter() {
+            // an empty clause has no bucket, and makes the conjunction false
+            if c.is_empty() {
+                return self.man.false_ptr();
+            }
+            let scope: HashSet<usize> = c.iter().map(|l| position[&l.get_label()]).collect();
+            let last = *scope.iter().max().unwrap();
+            let f = self.man.clause(c);
+            buckets[last].push((f, scope));
+        }
+        let mut done = Vec::new();
+        for pos in (0..order.len()).rev() {
+            if buckets[pos].is_empty() {
+                continue;
+            }
+            let bucket: Vec<(M::Ptr, HashSet<usize>)> = buckets[pos].drain(..).collect();
+            let fs: Vec<M::Ptr> = bucket.iter().map(|&(f, _)| f).collect();
+            let f = self.and_linear(&fs);
+            let mut scope: HashSet<usize> = bucket.into_iter().flat_map(|(_, s)| s).collect();
+            scope.remove(&pos);
+            match scope.iter().max().cloned() {
+                Some(next) => buckets[next].push((f, scope)),
+                None => done.push(f),
+            }
+        }
+        self.and_balanced(&done)
+    }
+
+    fn vtree(&mut self, vtree: &VTree, clauses: Vec<&Vec<Literal>>) -> M::Ptr {
+        match vtree {
+            &BTree::Leaf(_) => {
+                let fs: Vec<M::Ptr> = clauses.iter().map(|c| self.man.clause(c)).collect();
+                self.and_linear(&fs)
+            }
+            &BTree::Node(_, ref l, ref r) => {
+                let vars = |t: &VTree| -> HashSet<VarLabel> {
+                    t.in_order_iter()
+                        .filter_map(|n| match n {
+                            &BTree::Leaf(ref vars) => Some(vars.iter().cloned()),
+                            &BTree::Node(..) => None,
+                        })
+                        .flat_map(|vars| vars)
+                        .collect()
+                };
+                let (l_vars, r_vars) = (vars(l), vars(r));
+                let within = |c: &Vec<Literal>, vars: &HashSet<VarLabel>| {
+                    c.iter().all(|x| vars.contains(&x.get_label()))
+                };
+                let (left, rest): (Vec<&Vec<Literal>>, Vec<&Vec<Literal>>) =
+                    clauses.into_iter().partition(|c| within(c, &l_vars));
+                let (right, here): (Vec<&Vec<Literal>>, Vec<&Vec<Literal>>) =
+                    rest.into_iter().partition(|c| within(c, &r_vars));
+                // subtrees without clauses are true and are skipped, so that
+                // no conjunction has a constant argument
+                let mut fs = Vec::new();
+                if !left.is_empty() {
+                    fs.push(self.vtree(l, left));
+                }
+                if !right.is_empty() {
+                    fs.push(self.vtree(r, right));
+                }
+                for c in here.into_iter() {
+                    fs.push(self.man.clause(c));
+                }
+                self.and_linear(&fs)
+            }
+        }
+    }
+}
+
+fn run<M: Target>(
+    man: &mut M,
+    vtree: &VTree,
+    cnf: &Cnf,
+    opts: &ScheduleOptions,
+) -> (M::Ptr, Vec<usize>) {
+    let mut run = Run {
+        man: man,
+        sizes: if opts.record_sizes {
+            Some(Vec::new())
+        } else {
+            None
+        },
+    };
+    let r = match opts.schedule {
+        Schedule::Linear | Schedule::Balanced => {
+            let fs: Vec<M::Ptr> = cnf.clauses().iter().map(|c| run.man.clause(c)).collect();
+            if opts.schedule == Schedule::Linear {
+                run.and_linear(&fs)
+            } else {
+                run.and_balanced(&fs)
+            }
+        }
+        Schedule::Buckets => run.buckets(cnf.clauses()),
+        Schedule::VTree => run.vtree(vtree, cnf.clauses().iter().collect()),
+    };
+    (r, run.sizes.unwrap_or_default())
+}
+
+/// Compiles `cnf` into a BDD with the given schedule, returning the sizes of
+/// the intermediate results if they were requested
+pub fn compile_bdd(
+    man: &mut BddManager,
+    cnf: &Cnf,
+    opts: &ScheduleOptions,
+) -> (BddPtr, Vec<usize>) {
+    let vtree = right_linear(&Target::var_order(man), 1);
+    run(man, &vtree, cnf, opts)
+}
+
+/// Compiles `cnf` into an SDD with the given schedule, returning the sizes of
+/// the intermediate results if they were requested
+pub fn compile_sdd(
+    man: &mut SddManager,
+    cnf: &Cnf,
+    opts: &ScheduleOptions,
+) -> (SddPtr, Vec<usize>) {
+    let vtree = man.get_vtree_root().clone();
+    run(man, &vtree, cnf, opts)
+}
+
+#[cfg(test)]
+mod test_schedule {
+    use super::*;
+    use manager::sdd_manager::even_split;
+
+    const SCHEDULES: [Schedule; 4] = [
+        Schedule::Linear,
+        Schedule::Balanced,
+        Schedule::Buckets,
+        Schedule::VTree,
+    ];
+
+    #[test]
+    fn reports_sizes() {
+        let cnf = Cnf::from_file(String::from(include_str!("../../cnf/bench-01.cnf")));
+        let mut man = BddManager::new_default_order(cnf.num_vars());
+        let expected = man.from_cnf(&cnf);
+        for s in SCHEDULES.iter() {
+            let opts = ScheduleOptions {
+                schedule: *s,
+                record_sizes: true,
+            };
+            let (r, sizes) = compile_bdd(&mut man, &cnf, &opts);
+            assert_eq!(r, expected, "schedule {:?}", s);
+            assert_eq!(man.from_cnf_with(&cnf, &opts), r);
+            // one conjunction fewer than the clauses
+            assert_eq!(sizes.len(), cnf.clauses().len() - 1, "schedule {:?}", s);
+            assert_eq!(*sizes.last().unwrap(), man.count_nodes(r));
+        }
+    }
+
+    #[test]
+    fn empty_clause() {
+        let mut man = BddManager::new_default_order(2);
+        let x0 = Literal::new(VarLabel::new(0), true);
+        let mut run = Run {
+            man: &mut man,
+            sizes: None,
+        };
+        assert!(run.buckets(&[vec![x0], vec![]]).is_false());
+    }
+
+    quickcheck! {
+        fn schedules_agree(c: Cnf) -> bool {
+            let order: Vec<VarLabel> = (0..16).map(|v| VarLabel::new(v)).collect();
+            let mut bdd = BddManager::new_default_order(16);
+            let mut sdd = SddManager::new(even_split(&order, 3));
+            let bdd_expected = bdd.from_cnf(&c);
+            let sdd_expected = sdd.from_cnf(&c);
+            SCHEDULES.iter().all(|s| {
+                let opts = ScheduleOptions { schedule: *s, record_sizes: false };
+                let (b, _) = compile_bdd(&mut bdd, &c, &opts);
+                let (f, _) = compile_sdd(&mut sdd, &c, &opts);
+                b == bdd_expected && sdd.sdd_eq(f, sdd_expected)
+            })
+        }
+    }
+}
@@ -0,0 +1,239 @@
+//! A top-down compiler from CNF to decision diagrams.
+//!
+//! This is an exhaustive DPLL search: at each step the current clauses are
+//! simplified by unit propagation and split into connected components (sets
+//! of clauses which share no variables), which are compiled separately and
+//! conjoined. A component is compiled by branching on its first variable in
+//! the target's order, and the result is cached under the component's
+//! clauses, so identical sub-problems reached along different paths are
+//! compiled once. The trace of the search is a decision-DNNF; because
+//! decisions follow the target's variable order it is built directly as an
+//! OBDD (or as an SDD, with each decision applied as an `ite`).
+
+use super::Target;
+use manager::rsbdd_manager::BddManager;
+use manager::sdd_manager::SddManager;
+use repr::bdd::BddPtr;
+use repr::cnf::Cnf;
+use repr::sdd::SddPtr;
+use repr::var_label::{Literal, VarLabel};
+use std::collections::HashMap;
+
+/// Counters describing a top-down compilation
+#[derive(Debug, Clone, Default)]
+pub struct TopDownStats {
+    /// the number of variables branched on
+    pub decisions: usize,
+    /// the number of components found in the cache
+    pub cache_hits: usize,
+    /// the number of times the clauses split into more than one component
+    pub splits: usize,
+}
+
+fn negate(l: Literal) -> Literal {
+    Literal::new(l.get_label(), !l.get_polarity())
+}
+
+/// Sets `l` to true in `clauses`
+fn condition(clauses: &[Vec<Literal>], l: Literal) -> Vec<Vec<Literal>> {
+    let neg = negate(l);
+    clauses
+        .iter()
+        .filter(|c| !c.contains(&l))
+        .map(|c| c.iter().filter(|x| **x != neg).cloned().collect())
+        .collect()
+}
+
+/// Applies unit propagation to `clauses`, returning the implied literals and
+/// the remaining clauses, or None if a conflict is found
+fn propagate(mut clauses: Vec<Vec<Literal>>) -> Option<(Vec<Literal>, Vec<Vec<Literal>>)> {
+    let mut implied = Vec::new();
+    loop {
+        if clauses.iter().any(|c| c.is_empty()) {
+            return None;
+        }
+        let unit = match clauses.iter().find(|c| c.len() == 1) {
+            Some(c) => c[0],
+            None => return Some((implied, clauses)),
+        };
+        implied.push(unit);
+        clauses = condition(&clauses, unit);
+    }
+}
+
+/// Partitions `clauses` into sets which share no variables
+fn components(clauses: Vec<Vec<Literal>>) -> Vec<Vec<Vec<Literal>>> {
+    fn find(parent: &mut HashMap<VarLabel, VarLabel>, v: VarLabel) -> VarLabel {
+        let p = *parent.get(&v).unwrap_or(&v);
+        if p == v {
+            return v;
+        }
+        let r = find(parent, p);
+        parent.insert(v, r);
+        r
+    }
+    let mut parent: HashMap<VarLabel, VarLabel> = HashMap::new();
+    for c in clauses.iter() {
+        let first = find(&mut parent, c[0].get_label());
+        for l in c[1..].iter() {
+            let r = find(&mut parent, l.get_label());
+            if r != first {
+                parent.insert(r, first);
+            }
+        }
+    }
+    let mut idx: HashMap<VarLabel, usize> = HashMap::new();
+    let mut r: Vec<Vec<Vec<Literal>>> = Vec::new();
+    for c in clauses.into_iter() {
+        let root = find(&mut parent, c[0].get_label());
+        let i = *idx.entry(root).or_insert(r.len());
+        if i == r.len() {
+            r.push(Vec::new());
+        }
+        r[i].push(c);
+    }
+    r
+}
+
+/// A canonical form of a component, used as its cache key
+fn cache_key(clauses: &[Vec<Literal>]) -> Vec<Vec<u64>> {
+    let mut key: Vec<Vec<u64>> = clauses
+        .iter()
+        .map(|c| {
+            let mut c: Vec<u64> = c
+                .iter()
+                .map(|l| l.get_label().value() * 2 + l.get_polarity() as u64)
+                .collect();
+            c.sort();
+            c.dedup();
+            c
+        })
+        .collect();
+    key.sort();
+    key.dedup();
+    key
+}
+
+struct TopDown<'a, M: Target + 'a> {
+    man: &'a mut M,
+    /// the position of each variable in the order decisions are made in
+    position: HashMap<VarLabel, usize>,
+    cache: HashMap<Vec<Vec<u64>>, M::Ptr>,
+    stats: TopDownStats,
+}
+
+impl<'a, M: Target> TopDown<'a, M> {
+    fn compile(&mut self, clauses: Vec<Vec<Literal>>) -> M::Ptr {
+        let (implied, clauses) = match propagate(clauses) {
+            Some(r) => r,
+            None => return self.man.false_ptr(),
+        };
+        let comps = components(clauses);
+        if comps.len() > 1 {
+            self.stats.splits += 1;
+        }
+        let mut r = self.man.true_ptr();
+        for c in comps.into_iter() {
+            let sub = self.compile_component(c);
+            r = self.man.conj(r, sub);
+        }
+        for l in implied.into_iter() {
+            let v = self.man.lit(l);
+            r = self.man.conj(r, v);
+        }
+        r
+    }
+
+    fn compile_component(&mut self, clauses: Vec<Vec<Literal>>) -> M::Ptr {
+        let key = cache_key(&clauses);
+        if let Some(r) = self.cache.get(&key) {
+            self.stats.cache_hits += 1;
+            return *r;
+        }
+        self.stats.decisions += 1;
+        let position = &self.position;
+        let v = clauses
+            .iter()
+            .flat_map(|c| c.iter())
+            .map(|l| l.get_label())
+            .min_by_key(|v| position[v])
+            .unwrap();
+        let high = self.compile(condition(&clauses, Literal::new(v, true)));
+        let low = self.compile(condition(&clauses, Literal::new(v, false)));
+        let r = self.man.decide(v, low, high);
+        self.cache.insert(key, r);
+        r
+    }
+}
+
+fn run<M: Target>(man: &mut M, cnf: &Cnf) -> (M::Ptr, TopDownStats) {
+    let position = man
+        .var_order()
+        .into_iter()
+        .enumerate()
+        .map(|(i, v)| (v, i))
+        .collect();
+    let mut c = TopDown {
+        man: man,
+        position: position,
+        cache: HashMap::new(),
+        stats: TopDownStats::default(),
+    };
+    let r = c.compile(cnf.clauses().to_vec());
+    (r, c.stats)
+}
+
+/// Compiles `cnf` into a BDD by top-down search, branching in the manager's
+/// variable order
+pub fn compile_bdd(man: &mut BddManager, cnf: &Cnf) -> (BddPtr, TopDownStats) {
+    run(man, cnf)
+}
+
+/// Compiles `cnf` into an SDD for the manager's vtree by top-down search,
+/// branching on variables in the left-to-right order of the vtree's leaves
+pub fn compile_sdd(man: &mut SddManager, cnf: &Cnf) -> (SddPtr, TopDownStats) {
+    run(man, cnf)
+}
+
+#[cfg(test)]
+mod test_top_down {
+    use super::*;
+    use manager::sdd_manager::even_split;
+
+    #[test]
+    fn components_are_cached() {
+        let lit = |v: u64, p: bool| Literal::new(VarLabel::new(v), p);
+        // both values of x0 leave the component (x1 || x2), which is
+        // independent of (x3 || !x4)
+        let cnf = Cnf::new(vec![
+            vec![lit(0, true), lit(1, true), lit(2, true)],
+            vec![lit(0, false), lit(1, true), lit(2, true)],
+            vec![lit(3, true), lit(4, false)],
+        ]);
+        let mut man = BddManager::new_default_order(5);
+        let expected = man.from_cnf(&cnf);
+        let (r, stats) = compile_bdd(&mut man, &cnf);
+        assert!(man.eq_bdd(r, expected));
+        assert!(stats.splits > 0);
+        assert!(stats.cache_hits > 0);
+    }
+
+    quickcheck! {
+        fn top_down_bdd(c: Cnf) -> bool {
+            let mut man = BddManager::new_default_order(16);
+            let expected = man.from_cnf(&c);
+            let (r, _) = compile_bdd(&mut man, &c);
+            r == expected
+        }
+    }
+
+    quickcheck! {
+        fn top_down_sdd(c: Cnf) -> bool {
+            let order: Vec<VarLabel> = (0..16).map(|v| VarLabel::new(v)).collect();
+            let mut man = SddManager::new(even_split(&order, 3));
+            let expected = man.from_cnf(&c);
+            let (r, _) = compile_sdd(&mut man, &c);
+            man.sdd_eq(r, expected)
+        }
+    }
+}
@@ -16,8 +16,10 @@ extern crate time_test;
 #[macro_use]
 mod util;
 mod backing_store;
+pub mod compile;
 pub mod manager;
 pub mod repr;
+pub mod serialize;
 
 
 use manager::rsbdd_manager::BddManager;
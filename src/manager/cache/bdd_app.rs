@@ -1,6 +1,7 @@
 //! Apply cache for BDD operations
 use manager::cache::lru::*;
 use repr::bdd::*;
+use repr::var_label::VarLabel;
 
 const INITIAL_CAPACITY: usize = 20; // given as a power of two
 
@@ -18,31 +19,104 @@ impl Ite {
         // standardize the ite
         // See pgs. 115-117 of "Algorithms and Data Structures in VLSI Design"
         // first, introduce constants if possible
-        // let (f, g, h) = match (f, g, h) {
-        //     (f, g, h) if f == h => (f, g, BddPtr::false_node()),
-        //     (f, g, h) if f == h.neg() => (f, g, BddPtr::true_node()),
-        //     (f, g, h) if f == g.neg() => (f, BddPtr::false_node(), h),
-        //     _ => (f, g, h),
-        // };
-
-        // now, standardize for negation: ensure f and g are non-negated
-        // follow the table on p.g 116
-        // let (f, g, h, compl) = match (f, g, h) {
-        //     (f, g, h) if f.is_compl() && !h.is_compl() => (f.neg(), h, g, false),
-        //     (f, g, h) if !f.is_compl() && g.is_compl() => (f, g.neg(), h.neg(), true),
-        //     (f, g, h) if f.is_compl() && h.is_compl() => (f.neg(), h.neg(), g.neg(), true),
-        //     _ => (f, g, h, false),
-        // };
-        // assert!(!f.is_compl() && !g.is_compl());
-        // (Ite { f, g, h }, compl)
-        (Ite { f, g, h }, false)
+        let g = if g == f {
+            BddPtr::true_node()
+        } else if g == f.neg() {
+            BddPtr::false_node()
+        } else {
+            g
+        };
+        let h = if h == f {
+            BddPtr::false_node()
+        } else if h == f.neg() {
+            BddPtr::true_node()
+        } else {
+            h
+        };
+
+        // a triple which reduces to a single function `x` is stored as
+        // ite(T, x, x)
+        let terminal = if f.is_true() || g == h {
+            Some(g)
+        } else if f.is_false() {
+            Some(h)
+        } else if g.is_true() && h.is_false() {
+            Some(f)
+        } else if g.is_false() && h.is_true() {
+            Some(f.neg())
+        } else {
+            None
+        };
+        let (f, g, h) = match terminal {
+            Some(x) => (BddPtr::true_node(), x, x),
+            None => (f, g, h),
+        };
+
+        // for the symmetric operations, place the argument with the smallest
+        // regular pointer as f
+        let key = |p: BddPtr| p.regular().raw();
+        let (f, g, h) = match (f, g, h) {
+            // f | h
+            (f, g, h) if g.is_true() && key(h) < key(f) => (h, g, f),
+            // f & g
+            (f, g, h) if h.is_false() && key(g) < key(f) => (g, f, h),
+            // !f | g
+            (f, g, h) if h.is_true() && key(g) < key(f) => (g.neg(), f.neg(), h),
+            // !f & h
+            (f, g, h) if g.is_false() && key(h) < key(f) => (h.neg(), g, f.neg()),
+            // f <-> g
+            (f, g, h) if g == h.neg() && key(g) < key(f) => (g, f, f.neg()),
+            _ => (f, g, h),
+        };
+
+        // now, standardize for negation: ensure f and g are non-negated, using
+        // ite(!f, g, h) == ite(f, h, g) and ite(f, !g, !h) == !ite(f, g, h)
+        let (f, g, h) = if f.is_compl() {
+            (f.neg(), h, g)
+        } else {
+            (f, g, h)
+        };
+        if g.is_compl() {
+            (Ite { f: f, g: g.neg(), h: h.neg() }, true)
+        } else {
+            (Ite { f: f, g: g, h: h }, false)
+        }
+    }
+}
+
+/// The operations other than ITE whose results are cached, each applied to a
+/// single BDD `f`. Only operations producing a BDD are cached here; traversals
+/// like `count_nodes` and `wmc` produce other values and keep their own memo.
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Copy)]
+pub enum BddOp {
+    /// `f | lbl = value`
+    Condition(VarLabel, bool),
+    /// `exists lbl. f`
+    Exists(VarLabel),
+    /// `f` with `g` substituted for `lbl`
+    Compose(VarLabel, BddPtr),
+}
+
+impl BddOp {
+    /// True if `op(!f) == !op(f)`, so that `f` and `!f` can share an entry
+    fn commutes_with_neg(&self) -> bool {
+        match *self {
+            BddOp::Condition(..) | BddOp::Compose(..) => true,
+            BddOp::Exists(..) => false,
+        }
     }
 }
 
+/// Keys of the apply table; each operation has its own entries
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Copy)]
+enum BddApply {
+    Ite(Ite),
+    Op(BddOp, BddPtr),
+}
+
 /// The top-level data structure that caches applications
 pub struct BddApplyTable {
-    /// a vector of applications, indexed by the top label of the first pointer.
-    table: Lru<Ite, BddPtr>,
+    table: Lru<BddApply, BddPtr>,
 }
 
 impl BddApplyTable {
@@ -57,14 +131,14 @@ impl BddApplyTable {
     pub fn insert(&mut self, f: BddPtr, g: BddPtr, h: BddPtr, res: BddPtr) -> () {
         // convert the ITE into a canonical form
         let (ite, compl) = Ite::new(f, g, h);
-        self.table.insert(ite, if compl { res.neg() } else { res });
+        self.table.insert(BddApply::Ite(ite), if compl { res.neg() } else { res });
         // println!("Inserted Ite({:?}, {:?}, {:?}, standardized {:?}", f, g, h, ite);
     }
 
     pub fn get(&mut self, f: BddPtr, g: BddPtr, h: BddPtr) -> Option<BddPtr> {
         let (ite, compl) = Ite::new(f, g, h);
         // println!("Looking up Ite({:?}, {:?}, {:?}, standardized {:?}\n", f, g, h, ite);
-        let r = self.table.get(ite);
+        let r = self.table.get(BddApply::Ite(ite));
         if compl {
             r.map(|v| v.neg())
         } else {
@@ -72,6 +146,23 @@ impl BddApplyTable {
         }
     }
 
+    /// Insert the result `res` of `op` applied to `f`
+    pub fn insert_op(&mut self, op: BddOp, f: BddPtr, res: BddPtr) -> () {
+        if op.commutes_with_neg() && f.is_compl() {
+            self.table.insert(BddApply::Op(op, f.neg()), res.neg());
+        } else {
+            self.table.insert(BddApply::Op(op, f), res);
+        }
+    }
+
+    pub fn get_op(&mut self, op: BddOp, f: BddPtr) -> Option<BddPtr> {
+        if op.commutes_with_neg() && f.is_compl() {
+            self.table.get(BddApply::Op(op, f.neg())).map(|v| v.neg())
+        } else {
+            self.table.get(BddApply::Op(op, f))
+        }
+    }
+
     pub fn get_stats(&self) -> ApplyCacheStats {
         self.table.get_stats()
     }
@@ -85,6 +176,12 @@ impl BddApplyTable {
 #[cfg(test)]
 mod test_bdd_apply_table {
     use BddPtr;
+    use super::BddOp;
+    use repr::bdd::TableIndex;
+    use manager::rsbdd_manager::BddManager;
+    use repr::cnf::Cnf;
+    use repr::var_label::VarLabel;
+    use std::collections::HashMap;
   quickcheck! {
       fn insert_eq(f: BddPtr, g: BddPtr, h: BddPtr, r: BddPtr) -> bool {
           let mut tbl = super::BddApplyTable::new();
@@ -106,5 +203,66 @@ mod test_bdd_apply_table {
           }
       }
   }
-}
 
+  quickcheck! {
+      /// ite(f, g, h) == ite(!f, h, g) == !ite(f, !g, !h)
+      fn insert_eq_compl(f: BddPtr, g: BddPtr, h: BddPtr, r: BddPtr) -> bool {
+          let mut tbl = super::BddApplyTable::new();
+          tbl.insert(f, g, h, r);
+          tbl.get(f.neg(), h, g) == Some(r) && tbl.get(f, g.neg(), h.neg()) == Some(r.neg())
+      }
+  }
+
+  quickcheck! {
+      /// the symmetric operations hit the same entry with their arguments swapped
+      fn insert_eq_sym(f: BddPtr, g: BddPtr, r: BddPtr) -> bool {
+          let (t, fl) = (BddPtr::true_node(), BddPtr::false_node());
+          let mut tbl = super::BddApplyTable::new();
+          tbl.insert(f, g, fl, r);
+          let and = tbl.get(g, f, fl) == Some(r);
+          tbl.insert(f, t, g, r);
+          let or = tbl.get(g, t, f) == Some(r);
+          tbl.insert(f, g, g.neg(), r);
+          let iff = tbl.get(g, f, f.neg()) == Some(r);
+          and && or && iff
+      }
+  }
+
+  quickcheck! {
+      /// the standard triple computes the same function as the original one
+      fn standard_sound(c1: Cnf, c2: Cnf, c3: Cnf, idx: (u8, u8, u8)) -> bool {
+          let mut man = BddManager::new_default_order(8);
+          let (f, g, h) = (man.from_cnf(&c1), man.from_cnf(&c2), man.from_cnf(&c3));
+          let pool = [f, g, h, f.neg(), g.neg(), h.neg(), BddPtr::true_node(), BddPtr::false_node()];
+          let (a, b, c) = (pool[idx.0 as usize % 8], pool[idx.1 as usize % 8], pool[idx.2 as usize % 8]);
+          let (ite, compl) = super::Ite::new(a, b, c);
+          (0..256).all(|bits: u32| {
+              let assgn: HashMap<VarLabel, bool> =
+                  (0..8).map(|v| (VarLabel::new(v), bits & (1 << v) != 0)).collect();
+              let eval = |p| man.eval_bdd(p, &assgn);
+              let orig = if eval(a) { eval(b) } else { eval(c) };
+              let std = if eval(ite.f) { eval(ite.g) } else { eval(ite.h) };
+              orig == (std != compl)
+          })
+      }
+  }
+
+  quickcheck! {
+      /// entries of different operations on the same BDD do not collide
+      fn insert_op_distinct(f: BddPtr, g: BddPtr, v: u8) -> bool {
+          let v = VarLabel::new(v as u64);
+          let ops = [BddOp::Condition(v, true), BddOp::Condition(v, false),
+                     BddOp::Exists(v), BddOp::Compose(v, g)];
+          let res = |i: usize| BddPtr::new(VarLabel::new(i as u64), TableIndex::new(1));
+          let mut tbl = super::BddApplyTable::new();
+          for (i, op) in ops.iter().enumerate() {
+              tbl.insert_op(*op, f, res(i));
+          }
+          let ite = tbl.get(f, BddPtr::true_node(), BddPtr::false_node()).is_none();
+          let exists_neg = tbl.get_op(BddOp::Exists(v), f.neg()).is_none();
+          let cond_neg = tbl.get_op(BddOp::Condition(v, true), f.neg()) == Some(res(0).neg());
+          ite && exists_neg && cond_neg
+              && ops.iter().enumerate().all(|(i, op)| tbl.get_op(*op, f) == Some(res(i)))
+      }
+  }
+}
@@ -138,6 +138,14 @@ where
         // return None;
     }
 
+    /// remove every entry from the cache, keeping its capacity
+    pub fn clear(&mut self) -> () {
+        for i in self.tbl.iter_mut() {
+            *i = None;
+        }
+        self.len = 0;
+    }
+
     /// grow the hashtable to accomodate more elements
     fn grow(&mut self) -> () {
         let new_sz = self.cap + 1;
@@ -0,0 +1,337 @@
+//! Finite-domain variables and bit-vector arithmetic on top of a `BddManager`.
+//!
+//! A `Domain` is a variable ranging over `0..size`, encoded by a block of BDD
+//! variables allocated at the end of the manager's order. Every domain can be
+//! viewed as a bit-vector (a `Vec<BddPtr>` of its bits, least significant
+//! first), and comparisons and arithmetic are built on bit-vectors, so they
+//! work across encodings. They constrain only the encoded value: conjoin
+//! `Domain::valid` to rule out the assignments which encode no value.
+
+use manager::rsbdd_manager::BddManager;
+use repr::bdd::BddPtr;
+use repr::var_label::VarLabel;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    /// `ceil(log2(size))` variables holding the value in binary
+    Binary,
+    /// one variable per value, exactly one of which is true
+    OneHot,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Domain {
+    vars: Vec<VarLabel>,
+    size: u64,
+    encoding: Encoding,
+}
+
+/// The number of bits needed to hold every value below `size`
+fn width(size: u64) -> usize {
+    64 - (size - 1).leading_zeros() as usize
+}
+
+/// The bit-vector of the constant `v`, `width` bits wide
+pub fn bv_const(man: &BddManager, v: u64, width: usize) -> Vec<BddPtr> {
+    (0..width)
+        .map(|i| {
+            if i < 64 && v & (1 << i) != 0 {
+                man.true_ptr()
+            } else {
+                man.false_ptr()
+            }
+        })
+        .collect()
+}
+
+/// Bit `i` of `a`, which is false beyond its width
+fn bit(man: &BddManager, a: &[BddPtr], i: usize) -> BddPtr {
+    a.get(i).cloned().unwrap_or(man.false_ptr())
+}
+
+/// `a == b`, where the shorter vector is padded with zeros
+pub fn bv_eq(man: &mut BddManager, a: &[BddPtr], b: &[BddPtr]) -> BddPtr {
+    let mut r = man.true_ptr();
+    for i in 0..a.len().max(b.len()) {
+        let (x, y) = (bit(man, a, i), bit(man, b, i));
+        let e = man.iff(x, y);
+        r = man.and(r, e);
+    }
+    r
+}
+
+/// `a < b` as unsigned integers
+pub fn bv_lt(man: &mut BddManager, a: &[BddPtr], b: &[BddPtr]) -> BddPtr {
+    // from the least significant bit up, `a < b` on the bits so far is decided
+    // by the current bit unless the bits are equal
+    let mut r = man.false_ptr();
+    for i in 0..a.len().max(b.len()) {
+        let (x, y) = (bit(man, a, i), bit(man, b, i));
+        let nx = man.negate(x);
+        let lt = man.and(nx, y);
+        let eq = man.iff(x, y);
+        let rest = man.and(eq, r);
+        r = man.or(lt, rest);
+    }
+    r
+}
+
+/// `a + b`, one bit wider than the wider argument so that it never overflows
+pub fn bv_add(man: &mut BddManager, a: &[BddPtr], b: &[BddPtr]) -> Vec<BddPtr> {
+    let mut carry = man.false_ptr();
+    let mut r = Vec::new();
+    for i in 0..a.len().max(b.len()) {
+        let (x, y) = (bit(man, a, i), bit(man, b, i));
+        let xy = man.xor(x, y);
+        r.push(man.xor(xy, carry));
+        let both = man.and(x, y);
+        let propagate = man.and(xy, carry);
+        carry = man.or(both, propagate);
+    }
+    r.push(carry);
+    r
+}
+
+impl Domain {
+    /// Allocates the variables of a new domain with values `0..size`
+    pub fn new(man: &mut BddManager, size: u64, encoding: Encoding) -> Domain {
+        assert!(size > 0, "a domain must have at least one value");
+        let n = match encoding {
+            Encoding::Binary => width(size),
+            Encoding::OneHot => size as usize,
+        };
+        Domain {
+            vars: (0..n).map(|_| man.new_var()).collect(),
+            size: size,
+            encoding: encoding,
+        }
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    /// The variables encoding this domain
+    pub fn vars(&self) -> &[VarLabel] {
+        &self.vars
+    }
+
+    /// True for exactly the assignments to `vars` which encode a value
+    pub fn valid(&self, man: &mut BddManager) -> BddPtr {
+        match self.encoding {
+            Encoding::Binary => {
+                let bits = self.bits(man);
+                let size = bv_const(man, self.size, bits.len() + 1);
+                bv_lt(man, &bits, &size)
+            }
+            Encoding::OneHot => {
+                // (at most one true so far, exactly one true so far)
+                let (mut none, mut one) = (man.true_ptr(), man.false_ptr());
+                for v in self.vars.iter() {
+                    let (x, nx) = (man.var(*v, true), man.var(*v, false));
+                    let keep = man.and(one, nx);
+                    let set = man.and(none, x);
+                    one = man.or(keep, set);
+                    none = man.and(none, nx);
+                }
+                one
+            }
+        }
+    }
+
+    /// This domain as a bit-vector, least significant bit first
+    pub fn bits(&self, man: &mut BddManager) -> Vec<BddPtr> {
+        match self.encoding {
+            Encoding::Binary => self.vars.iter().map(|v| man.var(*v, true)).collect(),
+            Encoding::OneHot => (0..width(self.size))
+                .map(|i| {
+                    let vals: Vec<BddPtr> = self
+                        .vars
+                        .iter()
+                        .enumerate()
+                        .filter(|&(val, _)| val & (1 << i) != 0)
+                        .map(|(_, v)| man.var(*v, true))
+                        .collect();
+                    man.or_lst(&vals)
+                })
+                .collect(),
+        }
+    }
+
+    /// This domain is equal to `v`
+    pub fn eq_const(&self, man: &mut BddManager, v: u64) -> BddPtr {
+        match self.encoding {
+            Encoding::Binary => {
+                let bits = self.bits(man);
+                let c = bv_const(man, v, 64);
+                bv_eq(man, &bits, &c)
+            }
+            Encoding::OneHot if v < self.size => man.var(self.vars[v as usize], true),
+            Encoding::OneHot => man.false_ptr(),
+        }
+    }
+
+    /// This domain is equal to `other`
+    pub fn eq(&self, man: &mut BddManager, other: &Domain) -> BddPtr {
+        let (a, b) = (self.bits(man), other.bits(man));
+        bv_eq(man, &a, &b)
+    }
+
+    /// This domain is less than `other`
+    pub fn lt(&self, man: &mut BddManager, other: &Domain) -> BddPtr {
+        let (a, b) = (self.bits(man), other.bits(man));
+        bv_lt(man, &a, &b)
+    }
+
+    /// This domain is at most `other`
+    pub fn le(&self, man: &mut BddManager, other: &Domain) -> BddPtr {
+        let gt = other.lt(man, self);
+        man.negate(gt)
+    }
+
+    /// This domain is less than `v`
+    pub fn lt_const(&self, man: &mut BddManager, v: u64) -> BddPtr {
+        let a = self.bits(man);
+        let c = bv_const(man, v, 64);
+        bv_lt(man, &a, &c)
+    }
+
+    /// `a + b == self`
+    pub fn sum_of(&self, man: &mut BddManager, a: &Domain, b: &Domain) -> BddPtr {
+        let (x, y, z) = (a.bits(man), b.bits(man), self.bits(man));
+        let s = bv_add(man, &x, &y);
+        bv_eq(man, &s, &z)
+    }
+
+    /// The value encoded by `assgn`, or None if it encodes none
+    pub fn decode(&self, assgn: &HashMap<VarLabel, bool>) -> Option<u64> {
+        let v = match self.encoding {
+            Encoding::Binary => self
+                .vars
+                .iter()
+                .enumerate()
+                .filter(|&(_, v)| assgn[v])
+                .fold(0, |acc, (i, _)| acc | (1 << i)),
+            Encoding::OneHot => {
+                let set: Vec<usize> = (0..self.vars.len())
+                    .filter(|i| assgn[&self.vars[*i]])
+                    .collect();
+                if set.len() != 1 {
+                    return None;
+                }
+                set[0] as u64
+            }
+        };
+        if v < self.size {
+            Some(v)
+        } else {
+            None
+        }
+    }
+
+    /// The assignment to `vars` which encodes `v`
+    pub fn encode(&self, v: u64) -> HashMap<VarLabel, bool> {
+        assert!(v < self.size, "{} is not in the domain", v);
+        self.vars
+            .iter()
+            .enumerate()
+            .map(|(i, var)| {
+                let b = match self.encoding {
+                    Encoding::Binary => v & (1 << i) != 0,
+                    Encoding::OneHot => i as u64 == v,
+                };
+                (*var, b)
+            })
+            .collect()
+    }
+
+    /// The values this domain takes in the models of `f`
+    pub fn values(&self, man: &mut BddManager, f: BddPtr) -> Vec<u64> {
+        let valid = self.valid(man);
+        let f = man.and(f, valid);
+        (0..self.size)
+            .filter(|v| {
+                let e = self.eq_const(man, *v);
+                man.intersects(f, e)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test_fdd {
+    use super::*;
+
+    fn check(enc_a: Encoding, enc_b: Encoding) {
+        let mut man = BddManager::new_default_order(0);
+        let a = Domain::new(&mut man, 5, enc_a);
+        let b = Domain::new(&mut man, 3, enc_b);
+        let c = Domain::new(&mut man, 7, Encoding::Binary);
+        let eq = a.eq(&mut man, &b);
+        let lt = a.lt(&mut man, &b);
+        let le = a.le(&mut man, &b);
+        let lt2 = a.lt_const(&mut man, 2);
+        let sum = c.sum_of(&mut man, &a, &b);
+        for va in 0..5 {
+            for vb in 0..3 {
+                let mut assgn = a.encode(va);
+                assgn.extend(b.encode(vb));
+                assert_eq!(a.decode(&assgn), Some(va));
+                assert_eq!(b.decode(&assgn), Some(vb));
+                assert_eq!(man.eval_bdd(eq, &assgn), va == vb);
+                assert_eq!(man.eval_bdd(lt, &assgn), va < vb);
+                assert_eq!(man.eval_bdd(le, &assgn), va <= vb);
+                assert_eq!(man.eval_bdd(lt2, &assgn), va < 2);
+                for vc in 0..7 {
+                    assgn.extend(c.encode(vc));
+                    assert_eq!(man.eval_bdd(sum, &assgn), va + vb == vc);
+                }
+            }
+        }
+        // the sums of the pairs with a < b
+        let valid = [a.valid(&mut man), b.valid(&mut man), c.valid(&mut man)];
+        let v = man.and(sum, lt);
+        let v = valid.iter().fold(v, |acc, f| man.and(acc, *f));
+        assert_eq!(c.values(&mut man, v), vec![1, 2, 3]);
+        assert_eq!(a.values(&mut man, v), vec![0, 1]);
+    }
+
+    #[test]
+    fn binary() {
+        check(Encoding::Binary, Encoding::Binary);
+    }
+
+    #[test]
+    fn one_hot() {
+        check(Encoding::OneHot, Encoding::OneHot);
+        check(Encoding::OneHot, Encoding::Binary);
+    }
+
+    #[test]
+    fn valid() {
+        let mut man = BddManager::new_default_order(0);
+        for &enc in [Encoding::Binary, Encoding::OneHot].iter() {
+            let d = Domain::new(&mut man, 5, enc);
+            let valid = d.valid(&mut man);
+            let n = d.vars().len();
+            let mut count = 0;
+            for bits in 0..(1u64 << n) {
+                let assgn: HashMap<VarLabel, bool> = d
+                    .vars()
+                    .iter()
+                    .enumerate()
+                    .map(|(i, v)| (*v, bits & (1 << i) != 0))
+                    .collect();
+                let ok = man.eval_bdd(valid, &assgn);
+                assert_eq!(ok, d.decode(&assgn).is_some());
+                count += ok as usize;
+            }
+            assert_eq!(count, 5);
+        }
+    }
+}
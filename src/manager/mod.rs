@@ -1,6 +1,7 @@
 mod cache;
 
 pub mod cudd_manager;
+pub mod fdd;
 mod ref_table;
 pub mod rsbdd_manager;
 pub mod sdd_manager;
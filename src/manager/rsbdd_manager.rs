@@ -2,6 +2,8 @@
 
 use backing_store::bdd_table_robinhood::BddTable;
 use backing_store::BackingCacheStats;
+use compile;
+use compile::schedule::ScheduleOptions;
 use manager::cache::bdd_app::*;
 use manager::cache::lru::ApplyCacheStats;
 use manager::var_order::VarOrder;
@@ -9,7 +11,9 @@ use num::traits::Num;
 use repr::bdd::*;
 use repr::boolexpr::BoolExpr;
 use repr::cnf::Cnf;
-use repr::var_label::VarLabel;
+use repr::pb::{Opb, PbConstraint};
+use repr::var_label::{Literal, VarLabel, VarNames};
+use serialize::ParseError;
 use std::cmp::Ordering;
 use std::collections::{HashMap, HashSet};
 use std::fmt::Debug;
@@ -80,6 +84,7 @@ pub struct BddManager {
     compute_table: BddTable,
     apply_table: BddApplyTable,
     stats: BddManagerStats,
+    names: VarNames,
 }
 
 impl BddManager {
@@ -94,6 +99,7 @@ impl BddManager {
             compute_table: BddTable::new(order),
             apply_table: BddApplyTable::new(),
             stats: BddManagerStats::new(),
+            names: VarNames::new(),
         }
     }
 
@@ -109,6 +115,28 @@ impl BddManager {
         self.compute_table.order()
     }
 
+    /// The names of the manager's variables, used when printing and exporting
+    pub fn names(&self) -> &VarNames {
+        &self.names
+    }
+
+    pub fn names_mut(&mut self) -> &mut VarNames {
+        &mut self.names
+    }
+
+    /// The variable named `name`, which is created at the end of the order if
+    /// there is none
+    pub fn named_var(&mut self, name: &str) -> VarLabel {
+        match self.names.label(name) {
+            Some(lbl) => lbl,
+            None => {
+                let lbl = self.new_var();
+                self.names.insert(lbl, name);
+                lbl
+            }
+        }
+    }
+
     fn deref_bdd(&self, ptr: BddPtr) -> Bdd {
         self.compute_table.deref(ptr)
     }
@@ -171,6 +199,18 @@ impl BddManager {
         }
     }
 
+    /// Fetches the canonical node `if lbl then high else low` without
+    /// applying any operations; `lbl` must come before the top variables of
+    /// `low` and `high` in the order
+    pub fn mk_node(&mut self, lbl: VarLabel, low: BddPtr, high: BddPtr) -> BddPtr {
+        assert!(low.is_const() || self.get_order().lt(lbl, low.label()));
+        assert!(high.is_const() || self.get_order().lt(lbl, high.label()));
+        if low == high {
+            return low;
+        }
+        self.get_or_insert(BddNode::new(low, high, lbl))
+    }
+
     pub fn print_bdd(&self, ptr: BddPtr) -> String {
         use repr::bdd::PointerType::*;
         fn print_bdd_helper(t: &BddManager, ptr: BddPtr) -> String {
@@ -190,7 +230,7 @@ impl BddManager {
                     };
                     let l_s = print_bdd_helper(t, l_p);
                     let h_s = print_bdd_helper(t, h_p);
-                    format!("({}, {}, {})", ptr.var(), h_s, l_s)
+                    format!("({}, {}, {})", t.names.display(ptr.label()), h_s, l_s)
                 }
             }
         }
@@ -202,11 +242,23 @@ impl BddManager {
     }
 
     pub fn print_bdd_lbl(&self, ptr: BddPtr, map: &HashMap<VarLabel, VarLabel>) -> String {
+        self.print_bdd_lbl_named(ptr, map, &VarNames::new())
+    }
+
+    /// Prints `ptr` after relabeling its variables by `map`, showing the
+    /// relabeled variables by their name in `names`
+    pub fn print_bdd_lbl_named(
+        &self,
+        ptr: BddPtr,
+        map: &HashMap<VarLabel, VarLabel>,
+        names: &VarNames,
+    ) -> String {
         use repr::bdd::PointerType::*;
         fn print_bdd_helper(
             t: &BddManager,
             ptr: BddPtr,
             map: &HashMap<VarLabel, VarLabel>,
+            names: &VarNames,
         ) -> String {
             match ptr.ptr_type() {
                 PtrTrue => String::from("T"),
@@ -214,11 +266,11 @@ impl BddManager {
                 PtrNode => {
                     let l_p = t.low(ptr);
                     let h_p = t.high(ptr);
-                    let l_s = print_bdd_helper(t, l_p, map);
-                    let r_s = print_bdd_helper(t, h_p, map);
+                    let l_s = print_bdd_helper(t, l_p, map, names);
+                    let r_s = print_bdd_helper(t, h_p, map, names);
                     format!(
-                        "({:?}, {}{}, {}{})",
-                        map.get(&ptr.label()).unwrap().value(),
+                        "({}, {}{}, {}{})",
+                        names.display(*map.get(&ptr.label()).unwrap()),
                         if l_p.is_compl() { "!" } else { "" },
                         l_s,
                         if h_p.is_compl() { "!" } else { "" },
@@ -227,7 +279,7 @@ impl BddManager {
                 }
             }
         }
-        let s = print_bdd_helper(self, ptr, map);
+        let s = print_bdd_helper(self, ptr, map, names);
         format!("{}{}", if ptr.is_compl() { "!" } else { "" }, s)
     }
 
@@ -235,13 +287,68 @@ impl BddManager {
     pub fn compose(&mut self, f: BddPtr, lbl: VarLabel, g: BddPtr) -> BddPtr {
         // TODO this can be optimized with a specialized implementation to make
         // it a single traversal
-        let var = self.var(lbl, true);
-        let iff = self.iff(var, g);
-        let a = self.and(iff, f);
-        let r = self.exists(a, lbl);
+        if let Some(r) = self.apply_table.get_op(BddOp::Compose(lbl, g), f) {
+            return r;
+        }
+        // f[g/lbl] = ite(g, f|lbl, f|!lbl), which holds even if g mentions lbl
+        let t = self.condition(f, lbl, true);
+        let e = self.condition(f, lbl, false);
+        let r = self.ite(g, t, e);
+        self.apply_table.insert_op(BddOp::Compose(lbl, g), f, r);
         r
     }
 
+    /// Rebuilds the BDD `ptr` of the manager `other` in this manager, which
+    /// may have a different variable order. Every variable of `ptr` must be a
+    /// variable of this manager.
+    pub fn import(&mut self, other: &BddManager, ptr: BddPtr) -> BddPtr {
+        self.import_h(other, ptr, &|lbl| lbl, &mut HashMap::new())
+    }
+
+    /// Rebuilds the BDD `ptr` of the manager `other` in this manager,
+    /// relabeling each variable `v` of `ptr` to `map[v]`; panics if a variable
+    /// of `ptr` is missing from `map`
+    pub fn import_with_map(
+        &mut self,
+        other: &BddManager,
+        ptr: BddPtr,
+        map: &HashMap<VarLabel, VarLabel>,
+    ) -> BddPtr {
+        self.import_h(other, ptr, &|lbl| map[&lbl], &mut HashMap::new())
+    }
+
+    fn import_h<F>(
+        &mut self,
+        other: &BddManager,
+        ptr: BddPtr,
+        relabel: &F,
+        cache: &mut HashMap<BddPtr, BddPtr>,
+    ) -> BddPtr
+    where
+        F: Fn(VarLabel) -> VarLabel,
+    {
+        if ptr.is_const() {
+            return ptr;
+        }
+        let reg = ptr.regular();
+        let r = match cache.get(&reg) {
+            Some(v) => *v,
+            None => {
+                let l = self.import_h(other, other.low(reg), relabel, cache);
+                let h = self.import_h(other, other.high(reg), relabel, cache);
+                let v = self.var(relabel(other.topvar(reg)), true);
+                let r = self.ite(v, h, l);
+                cache.insert(reg, r);
+                r
+            }
+        };
+        if ptr.is_compl() {
+            r.neg()
+        } else {
+            r
+        }
+    }
+
     /// true if `a` represents a variable (both high and low are constant)
     pub fn is_var(&self, ptr: BddPtr) -> bool {
         match ptr.ptr_type() {
@@ -316,19 +423,21 @@ impl BddManager {
         let gxn = self.condition_essential(g, lbl, false);
         let hxn = self.condition_essential(h, lbl, false);
         let t = self.ite(fx, gx, hx);
-        let f = self.ite(fxn, gxn, hxn);
+        let e = self.ite(fxn, gxn, hxn);
 
-        if t == f {
+        if t == e {
             return t;
         };
 
         // now we have a new BDD
         let node = BddNode {
-            low: f,
+            low: e,
             high: t,
             var: lbl,
         };
         let r = self.get_or_insert(node);
+        // cached under the arguments of this call, so `f` must not be shadowed
+        // above
         self.apply_table.insert(f, g, h, r);
         r
     }
@@ -514,13 +623,8 @@ impl BddManager {
         }
     }
 
-    fn cond_helper(
-        &mut self,
-        bdd: BddPtr,
-        lbl: VarLabel,
-        value: bool,
-        cache: &mut HashMap<BddPtr, BddPtr>,
-    ) -> BddPtr {
+    /// Compute the Boolean function `f | var = value`
+    pub fn condition(&mut self, bdd: BddPtr, lbl: VarLabel, value: bool) -> BddPtr {
         self.stats.num_recursive_calls += 1;
         if self.get_order().lt(lbl, bdd.label()) || bdd.is_const() {
             // we passed the variable in the order, we will never find it
@@ -535,15 +639,16 @@ impl BddManager {
             }
         } else {
             // check cache
-            match cache.get(&bdd) {
+            let op = BddOp::Condition(lbl, value);
+            match self.apply_table.get_op(op, bdd) {
                 None => (),
-                Some(v) => return *v,
+                Some(v) => return v,
             };
 
             // recurse on the children
             let n = self.deref_bdd(bdd).into_node();
-            let l = self.cond_helper(n.low, lbl, value, cache);
-            let h = self.cond_helper(n.high, lbl, value, cache);
+            let l = self.condition(n.low, lbl, value);
+            let h = self.condition(n.high, lbl, value);
             if l == h {
                 if bdd.is_compl() {
                     return l.neg();
@@ -568,22 +673,38 @@ impl BddManager {
                 // nothing changed
                 bdd
             };
-            cache.insert(bdd, res);
+            self.apply_table.insert_op(op, bdd, res);
             res
         }
     }
 
-    /// Compute the Boolean function `f | var = value`
-    pub fn condition(&mut self, bdd: BddPtr, lbl: VarLabel, value: bool) -> BddPtr {
-        self.cond_helper(bdd, lbl, value, &mut HashMap::new())
-    }
-
     /// Existentially quantifies out the variable `lbl` from `f`
     pub fn exists(&mut self, bdd: BddPtr, lbl: VarLabel) -> BddPtr {
-        // TODO this can be optimized by specializing it
-        let v1 = self.condition(bdd, lbl, true);
-        let v2 = self.condition(bdd, lbl, false);
-        self.or(v1, v2)
+        self.stats.num_recursive_calls += 1;
+        if self.get_order().lt(lbl, bdd.label()) || bdd.is_const() {
+            // we passed the variable in the order, we will never find it
+            return bdd;
+        }
+        let op = BddOp::Exists(lbl);
+        match self.apply_table.get_op(op, bdd) {
+            None => (),
+            Some(v) => return v,
+        };
+        let n = self.deref_bdd(bdd).into_node();
+        let (low, high) = if bdd.is_compl() {
+            (n.low.neg(), n.high.neg())
+        } else {
+            (n.low, n.high)
+        };
+        let res = if bdd.label() == lbl {
+            self.or(low, high)
+        } else {
+            let l = self.exists(low, lbl);
+            let h = self.exists(high, lbl);
+            self.mk_node(bdd.label(), l, h)
+        };
+        self.apply_table.insert_op(op, bdd, res);
+        res
     }
 
     /// evaluates the top element of the data stack on the values found in
@@ -623,6 +744,95 @@ impl BddManager {
         a == b
     }
 
+    /// Returns true if `f && g` is satisfiable. Decided by a short-circuiting
+    /// traversal, so no BDD nodes are allocated
+    pub fn intersects(&self, f: BddPtr, g: BddPtr) -> bool {
+        self.intersects_h(f, g, &mut HashSet::new())
+    }
+
+    /// `seen` holds the pairs which were already visited; since a satisfiable
+    /// pair ends the search, every pair in it is unsatisfiable
+    fn intersects_h(
+        &self,
+        f: BddPtr,
+        g: BddPtr,
+        seen: &mut HashSet<(BddPtr, BddPtr)>,
+    ) -> bool {
+        // every BDD other than false has a model
+        if f.is_false() || g.is_false() {
+            return false;
+        } else if f.is_true() || g.is_true() || f == g {
+            return true;
+        } else if f == g.neg() {
+            return false;
+        }
+        let (f, g) = if f < g { (f, g) } else { (g, f) };
+        if !seen.insert((f, g)) {
+            return false;
+        }
+        let lbl = if self.get_order().lt(g.label(), f.label()) {
+            g.label()
+        } else {
+            f.label()
+        };
+        let fx = self.condition_essential(f, lbl, true);
+        let gx = self.condition_essential(g, lbl, true);
+        if self.intersects_h(fx, gx, seen) {
+            return true;
+        }
+        let fxn = self.condition_essential(f, lbl, false);
+        let gxn = self.condition_essential(g, lbl, false);
+        self.intersects_h(fxn, gxn, seen)
+    }
+
+    /// Returns true if `f` entails `g`, i.e. every model of `f` is a model of
+    /// `g`
+    pub fn implies(&self, f: BddPtr, g: BddPtr) -> bool {
+        !self.intersects(f, g.neg())
+    }
+
+    /// Returns true if `f` has a model which agrees with the partial
+    /// assignment `assgn`
+    pub fn sat_with(&self, f: BddPtr, assgn: &HashMap<VarLabel, bool>) -> bool {
+        fn helper(
+            man: &BddManager,
+            f: BddPtr,
+            assgn: &HashMap<VarLabel, bool>,
+            seen: &mut HashSet<BddPtr>,
+        ) -> bool {
+            if f.is_const() {
+                return f.is_true();
+            }
+            if !seen.insert(f) {
+                return false;
+            }
+            let lbl = f.label();
+            match assgn.get(&lbl) {
+                Some(v) => helper(man, man.condition_essential(f, lbl, *v), assgn, seen),
+                None => {
+                    helper(man, man.condition_essential(f, lbl, true), assgn, seen)
+                        || helper(man, man.condition_essential(f, lbl, false), assgn, seen)
+                }
+            }
+        }
+        helper(self, f, assgn, &mut HashSet::new())
+    }
+
+    /// Returns true if `f` entails the disjunction of the literals in `clause`
+    pub fn entails_clause(&self, f: BddPtr, clause: &[Literal]) -> bool {
+        // `f` entails the clause iff `f` has no model falsifying every literal
+        let mut assgn = HashMap::new();
+        for lit in clause.iter() {
+            let v = !lit.get_polarity();
+            match assgn.insert(lit.get_label(), v) {
+                // the clause contains both polarities of a variable
+                Some(prev) if prev != v => return true,
+                _ => (),
+            }
+        }
+        !self.sat_with(f, &assgn)
+    }
+
     pub fn get_backing_store_stats(&self) -> BackingCacheStats {
         self.compute_table.get_stats().clone()
     }
@@ -762,11 +972,38 @@ impl BddManager {
     //     }
     // }
 
-    /// Compile a BDD from a CNF
+    /// Compile a BDD from an expression in the syntax of `BoolExpr::parse`,
+    /// whose variables are named by the manager's names; new names are given
+    /// new variables
+    pub fn parse_expr(&mut self, s: &str) -> Result<BddPtr, ParseError> {
+        let e = BoolExpr::parse_with(s, &mut |name| {
+            Some(self.named_var(name).value() as usize)
+        })?;
+        Ok(self.from_boolexpr(&e))
+    }
+
+    /// Compile a BDD for a pseudo-Boolean constraint, one layer per variable
+    pub fn from_pb(&mut self, c: &PbConstraint) -> BddPtr {
+        compile::pb::compile_pb(self, c)
+    }
+
+    /// Compile a BDD for the conjunction of the constraints of an OPB file
+    pub fn from_opb(&mut self, opb: &Opb) -> BddPtr {
+        compile::pb::compile_opb(self, opb)
+    }
+
+    /// Compile a BDD from a CNF, conjoining its clauses in the order given by
+    /// `opts`
+    pub fn from_cnf_with(&mut self, cnf: &Cnf, opts: &ScheduleOptions) -> BddPtr {
+        compile::schedule::compile_bdd(self, cnf, opts).0
+    }
+
+    /// Compile a BDD from a CNF; a CNF with no clauses is true
     pub fn from_cnf(&mut self, cnf: &Cnf) -> BddPtr {
         let mut cvec: Vec<BddPtr> = Vec::with_capacity(cnf.clauses().len());
+        // a CNF with no clauses is true
         if cnf.clauses().is_empty() {
-            return BddPtr::false_node();
+            return BddPtr::true_node();
         }
 
         // sort the clauses based on a best-effort bottom-up ordering of clauses
@@ -861,6 +1098,43 @@ impl BddManager {
                 let r2 = self.from_boolexpr(r);
                 self.or(r1, r2)
             }
+            &BoolExpr::Const(v) => {
+                if v {
+                    self.true_ptr()
+                } else {
+                    self.false_ptr()
+                }
+            }
+            &BoolExpr::Not(ref e) => self.from_boolexpr(e).neg(),
+            &BoolExpr::Xor(ref l, ref r) => {
+                let r1 = self.from_boolexpr(l);
+                let r2 = self.from_boolexpr(r);
+                self.xor(r1, r2)
+            }
+            &BoolExpr::Iff(ref l, ref r) => {
+                let r1 = self.from_boolexpr(l);
+                let r2 = self.from_boolexpr(r);
+                self.iff(r1, r2)
+            }
+            &BoolExpr::Implies(ref l, ref r) => {
+                let r1 = self.from_boolexpr(l);
+                let r2 = self.from_boolexpr(r);
+                self.or(r1.neg(), r2)
+            }
+            &BoolExpr::Ite(ref c, ref t, ref e) => {
+                let r1 = self.from_boolexpr(c);
+                let r2 = self.from_boolexpr(t);
+                let r3 = self.from_boolexpr(e);
+                self.ite(r1, r2, r3)
+            }
+            &BoolExpr::Conj(ref v) => v.iter().fold(self.true_ptr(), |acc, e| {
+                let r = self.from_boolexpr(e);
+                self.and(acc, r)
+            }),
+            &BoolExpr::Disj(ref v) => v.iter().fold(self.false_ptr(), |acc, e| {
+                let r = self.from_boolexpr(e);
+                self.or(acc, r)
+            }),
         }
     }
 
@@ -1022,6 +1296,39 @@ fn test_exist_compl() {
     );
 }
 
+#[test]
+fn test_print_bdd_lbl_false() {
+    // complemented edges are marked, so false is the negation of the true node
+    let man = BddManager::new_default_order(1);
+    assert_eq!(man.print_bdd_lbl(BddPtr::false_node(), &HashMap::new()), "!T");
+}
+
+#[test]
+fn test_empty_cnf() {
+    // regression: a CNF with no clauses used to compile to false
+    let mut man = BddManager::new_default_order(2);
+    let r = man.from_cnf(&Cnf::new(vec![]));
+    assert!(r.is_true());
+}
+
+#[test]
+fn test_op_cache_persists() {
+    let mut man = BddManager::new_default_order(4);
+    let v: Vec<BddPtr> = (0..4).map(|i| man.var(VarLabel::new(i), true)).collect();
+    let a = man.iff(v[0], v[1]);
+    let b = man.xor(v[2], v[3]);
+    let f = man.or(a, b);
+    let lbl = VarLabel::new(3);
+    let (c1, e1) = (man.condition(f, lbl, true), man.exists(f, lbl));
+    let calls = man.num_recursive_calls();
+    // the second calls are answered by their first step
+    let (c2, e2) = (man.condition(f.neg(), lbl, true), man.exists(f, lbl));
+    assert_eq!(man.num_recursive_calls(), calls + 2);
+    assert_eq!((c2, e2), (c1.neg(), e1));
+    let c3 = man.condition(f, lbl, false);
+    assert!(c3 != c1);
+}
+
 #[test]
 fn test_compose() {
     let mut man = BddManager::new_default_order(3);
@@ -1192,7 +1499,9 @@ fn iff_regression() {
 #[cfg(test)]
 mod test_bdd_manager {
     use quickcheck::TestResult;
+    use repr::boolexpr::BoolExpr;
     use repr::cnf::Cnf;
+    use repr::pb::PbConstraint;
     use repr::var_label::Literal;
     use repr::var_label::VarLabel;
     use std::collections::HashMap;
@@ -1232,6 +1541,120 @@ mod test_bdd_manager {
         }
     }
 
+    quickcheck! {
+        fn implies_eq_apply(c1: Cnf, c2: Cnf) -> bool {
+            let mut mgr = super::BddManager::new_default_order(16);
+            let f = mgr.from_cnf(&c1);
+            let g = mgr.from_cnf(&c2);
+            let f_and_g = mgr.and(f, g);
+            let f_and_not_g = mgr.and(f, g.neg());
+            let entails = c2.clauses().iter().all(|clause| {
+                let c = mgr.from_cnf(&Cnf::new(vec![clause.clone()]));
+                mgr.entails_clause(f, clause) == mgr.implies(f, c)
+            });
+            mgr.intersects(f, g) == !f_and_g.is_false()
+                && mgr.implies(f, g) == f_and_not_g.is_false()
+                && mgr.implies(f_and_g, g)
+                && entails
+        }
+    }
+
+    quickcheck! {
+        fn import_eq_compile(c: Cnf, seed: usize) -> bool {
+            use manager::var_order::VarOrder;
+            use rand::{Rng, SeedableRng, StdRng};
+            let mut rng: StdRng = SeedableRng::from_seed(&[seed][..]);
+            let mut order: Vec<VarLabel> = (0..16).map(|x| VarLabel::new(x)).collect();
+            rng.shuffle(&mut order);
+            let mut src = super::BddManager::new_default_order(16);
+            let mut dst = super::BddManager::new(VarOrder::new(order));
+            let f = src.from_cnf(&c);
+            let expected = dst.from_cnf(&c);
+            let imported = dst.import(&src, f);
+
+            // relabel every variable `v` to `15 - v`
+            let map: HashMap<VarLabel, VarLabel> = HashMap::from_iter(
+                (0..16).map(|x| (VarLabel::new(x), VarLabel::new(15 - x))));
+            let flipped = c.clauses().iter().map(|clause| {
+                clause.iter().map(|lit| {
+                    Literal::new(map[&lit.get_label()], lit.get_polarity())
+                }).collect()
+            }).collect();
+            let expected_flip = dst.from_cnf(&Cnf::new(flipped));
+            let imported_flip = dst.import_with_map(&src, f, &map);
+            imported == expected && imported_flip == expected_flip
+        }
+    }
+
+    quickcheck! {
+        fn from_boolexpr_eval(e: BoolExpr) -> bool {
+            let mut man = super::BddManager::new_default_order(6);
+            let f = man.from_boolexpr(&e);
+            (0..64).all(|bits: u64| {
+                let assgn: HashMap<VarLabel, bool> = HashMap::from_iter(
+                    (0..6).map(|v| (VarLabel::new(v), bits & (1 << v) != 0)));
+                man.eval_bdd(f, &assgn) == e.eval(&assgn)
+            })
+        }
+    }
+
+    quickcheck! {
+        fn from_pb_eval(c: PbConstraint) -> bool {
+            let mut man = super::BddManager::new_default_order(6);
+            let f = man.from_pb(&c);
+            (0..64).all(|bits: u64| {
+                let assgn: HashMap<VarLabel, bool> = HashMap::from_iter(
+                    (0..6).map(|v| (VarLabel::new(v), bits & (1 << v) != 0)));
+                man.eval_bdd(f, &assgn) == c.eval(&assgn)
+            })
+        }
+    }
+
+    #[test]
+    fn named_vars() {
+        let mut man = super::BddManager::new_default_order(1);
+        let f = man.parse_expr("a & !b").unwrap();
+        // new names are given new variables after the existing ones
+        let a = man.names().label("a").unwrap();
+        let b = man.names().label("b").unwrap();
+        assert_eq!((a.value(), b.value()), (1, 2));
+        assert_eq!(man.print_bdd(f), "(a, (b, F, T), F)");
+        let g = man.parse_expr("!(b | !a)").unwrap();
+        assert_eq!(f, g);
+    }
+
+    #[test]
+    fn cardinality_size() {
+        let mut man = super::BddManager::new_default_order(20);
+        let vars: Vec<VarLabel> = (0..20).map(VarLabel::new).collect();
+        let f = man.from_pb(&PbConstraint::at_most(&vars, 3));
+        // one node per (variable, count so far) pair
+        assert!(man.count_nodes(f) <= 20 * 4);
+        let weights = (0..20).map(|v| (VarLabel::new(v), (1, 1))).collect();
+        let count = man.wmc(f, &super::BddWmc::new_with_default(0, 1, weights));
+        assert_eq!(count, 1 + 20 + 190 + 1140);
+    }
+
+    quickcheck! {
+        fn bdd_ite_eq_apply(c1: Cnf, c2: Cnf, c3: Cnf) -> bool {
+            let mut mgr = super::BddManager::new_default_order(16);
+            let f = mgr.from_cnf(&c1);
+            let g = mgr.from_cnf(&c2);
+            let h = mgr.from_cnf(&c3);
+            // compute the xor first so that its cached entries are live when
+            // the ite is computed
+            let xor = mgr.xor(f, g);
+            let ite = mgr.ite(f, g, h);
+            let fg = mgr.and(f, g);
+            let nfh = mgr.and(f.neg(), h);
+            let expected = mgr.or(fg, nfh);
+            let either = mgr.or(f, g);
+            let not_both = mgr.or(f.neg(), g.neg());
+            let expected_xor = mgr.and(either, not_both);
+            ite == expected && xor == expected_xor
+        }
+    }
+
     quickcheck! {
         fn wmc_eq(clauses: Vec<Vec<Literal>>) -> TestResult {
             let c1 = Cnf::new(clauses);
@@ -1253,4 +1676,34 @@ mod test_bdd_manager {
             TestResult::from_bool(bddres == cnfres)
         }
     }
+
+    quickcheck! {
+        fn ops_match_eval(c: Cnf, v: u8, w: u8) -> bool {
+            let mut mgr = super::BddManager::new_default_order(8);
+            let f = mgr.from_cnf(&c);
+            // `w` may be `v`, in which case `compose` negates `v`
+            let (v, w) = (VarLabel::new(v as u64 % 8), VarLabel::new(w as u64 % 8));
+            let g = mgr.var(w, false);
+            // each operation twice, so that the second one may be cached
+            let ops: Vec<_> = (0..2).map(|_| {
+                (mgr.condition(f, v, true), mgr.condition(f.neg(), v, false),
+                 mgr.exists(f, v), mgr.exists(f.neg(), v), mgr.compose(f, v, g))
+            }).collect();
+            let (cond_t, cond_f, ex, ex_neg, comp) = ops[0];
+            ops[0] == ops[1] && (0..256).all(|bits: u32| {
+                let assgn: HashMap<VarLabel, bool> =
+                    (0..8).map(|i| (VarLabel::new(i), bits & (1 << i) != 0)).collect();
+                let with = |b: bool| {
+                    let mut a = assgn.clone();
+                    a.insert(v, b);
+                    mgr.eval_bdd(f, &a)
+                };
+                mgr.eval_bdd(cond_t, &assgn) == with(true)
+                    && mgr.eval_bdd(cond_f, &assgn) == !with(false)
+                    && mgr.eval_bdd(ex, &assgn) == (with(true) || with(false))
+                    && mgr.eval_bdd(ex_neg, &assgn) == (!with(true) || !with(false))
+                    && mgr.eval_bdd(comp, &assgn) == with(!assgn[&w])
+            })
+        }
+    }
 }
@@ -2,15 +2,23 @@
 //! with SDDs.
 
 use backing_store::sdd_table::*;
+use compile;
+use compile::schedule::ScheduleOptions;
 use manager::cache::lru::*;
 use quickersort;
+use rand::{Rng, StdRng};
+use repr::bdd::BddPtr;
 use repr::boolexpr::BoolExpr;
 use repr::cnf::Cnf;
+use repr::pb::{Opb, PbConstraint};
 use repr::sdd::*;
-use repr::var_label::VarLabel;
+use repr::var_label::{Literal, VarLabel, VarNames};
+use serialize::ParseError;
+use std::cmp::max;
 use std::collections::{HashMap, HashSet};
 use util::btree::*;
 use std::fmt::Debug;
+use std::time::{Duration, Instant};
 use num::traits::Num;
 
 use super::rsbdd_manager::{BddManager, BddWmc};
@@ -94,6 +102,238 @@ pub fn even_split(order: &[VarLabel], num_splits: usize) -> VTree {
     }
 }
 
+/// generate a right-linear vtree from a variable ordering: each left child is a
+/// BDD leaf holding at most `leaf_size` variables, taken in order
+pub fn right_linear(order: &[VarLabel], leaf_size: usize) -> VTree {
+    assert!(leaf_size > 0, "leaf size must be positive");
+    if order.len() <= leaf_size {
+        BTree::Leaf(order.to_vec())
+    } else {
+        let (l_s, r_s) = order.split_at(leaf_size);
+        let r_tree = right_linear(r_s, leaf_size);
+        BTree::Node((), Box::new(BTree::Leaf(l_s.to_vec())), Box::new(r_tree))
+    }
+}
+
+/// generate a left-linear vtree from a variable ordering: each right child is a
+/// BDD leaf holding at most `leaf_size` variables, taken in order from the end
+pub fn left_linear(order: &[VarLabel], leaf_size: usize) -> VTree {
+    assert!(leaf_size > 0, "leaf size must be positive");
+    if order.len() <= leaf_size {
+        BTree::Leaf(order.to_vec())
+    } else {
+        let (l_s, r_s) = order.split_at(order.len() - leaf_size);
+        let l_tree = left_linear(l_s, leaf_size);
+        BTree::Node((), Box::new(l_tree), Box::new(BTree::Leaf(r_s.to_vec())))
+    }
+}
+
+/// generate a random vtree over the variables in `order`: the variables are
+/// shuffled and then split at random points until each BDD leaf holds at most
+/// `leaf_size` variables
+pub fn rand_vtree(rng: &mut StdRng, order: &[VarLabel], leaf_size: usize) -> VTree {
+    fn helper(rng: &mut StdRng, order: &[VarLabel], leaf_size: usize) -> VTree {
+        if order.len() <= leaf_size {
+            BTree::Leaf(order.to_vec())
+        } else {
+            let (l_s, r_s) = order.split_at(rng.gen_range(1, order.len()));
+            let l_tree = helper(rng, l_s, leaf_size);
+            let r_tree = helper(rng, r_s, leaf_size);
+            BTree::Node((), Box::new(l_tree), Box::new(r_tree))
+        }
+    }
+    assert!(leaf_size > 0, "leaf size must be positive");
+    let mut shuffled = order.to_vec();
+    rng.shuffle(&mut shuffled);
+    helper(rng, &shuffled, leaf_size)
+}
+
+/// generate a vtree guided by the structure of `cnf`, in the style of a dtree
+/// built by recursive hypergraph partitioning: the clauses are treated as
+/// hyperedges over the variables, and each vtree node splits its variables into
+/// two balanced halves that cut as few clauses as possible. Splitting stops
+/// once a set holds at most `leaf_size` variables, which becomes a BDD leaf.
+pub fn cnf_vtree(cnf: &Cnf, leaf_size: usize) -> VTree {
+    assert!(leaf_size > 0, "leaf size must be positive");
+    // start from an order which keeps clauses local; the initial bisection of
+    // every set is taken from it
+    let order = cnf.force_order();
+    let mut vars: Vec<VarLabel> = (0..cnf.num_vars())
+        .map(|x| VarLabel::new(x as u64))
+        .collect();
+    vars.sort_by_key(|v| order.get(*v));
+    // index from each variable to the clauses that mention it
+    let mut occurs: Vec<Vec<usize>> = vec![Vec::new(); cnf.num_vars()];
+    for (idx, clause) in cnf.clauses().iter().enumerate() {
+        for lit in clause.iter() {
+            let v = lit.get_label().value() as usize;
+            if occurs[v].last() != Some(&idx) {
+                occurs[v].push(idx);
+            }
+        }
+    }
+
+    /// split `vars` into two halves with a small hyperedge cut, using
+    /// single-variable moves in the style of Fiduccia-Mattheyses
+    fn bisect(
+        vars: &[VarLabel],
+        occurs: &Vec<Vec<usize>>,
+        num_clauses: usize,
+    ) -> (Vec<VarLabel>, Vec<VarLabel>) {
+        // each side keeps at least a third of the variables to stay balanced
+        let min_side = max(1, vars.len() / 3);
+        let mut in_left: HashMap<VarLabel, bool> = HashMap::new();
+        for (idx, v) in vars.iter().enumerate() {
+            in_left.insert(*v, idx < vars.len() / 2);
+        }
+        let mut num_left = vars.len() / 2;
+        // count how many variables of each clause lie on either side
+        let mut cnt: Vec<(usize, usize)> = vec![(0, 0); num_clauses];
+        for v in vars.iter() {
+            for c in occurs[v.value() as usize].iter() {
+                if in_left[v] {
+                    cnt[*c].0 += 1;
+                } else {
+                    cnt[*c].1 += 1;
+                }
+            }
+        }
+        let mut moved: HashSet<VarLabel> = HashSet::new();
+        loop {
+            // find the unmoved variable whose move most reduces the cut
+            let mut best: Option<(VarLabel, isize)> = None;
+            for v in vars.iter() {
+                if moved.contains(v) {
+                    continue;
+                }
+                let from_left = in_left[v];
+                let from_sz = if from_left { num_left } else { vars.len() - num_left };
+                if from_sz <= min_side {
+                    continue;
+                }
+                let mut gain: isize = 0;
+                for c in occurs[v.value() as usize].iter() {
+                    let (from, to) = if from_left { cnt[*c] } else { (cnt[*c].1, cnt[*c].0) };
+                    if from == 1 && to > 0 {
+                        gain += 1;
+                    } else if to == 0 && from > 1 {
+                        gain -= 1;
+                    }
+                }
+                if best.map_or(true, |(_, g)| gain > g) {
+                    best = Some((*v, gain));
+                }
+            }
+            match best {
+                Some((v, gain)) if gain > 0 => {
+                    let from_left = in_left[&v];
+                    for c in occurs[v.value() as usize].iter() {
+                        if from_left {
+                            cnt[*c] = (cnt[*c].0 - 1, cnt[*c].1 + 1);
+                        } else {
+                            cnt[*c] = (cnt[*c].0 + 1, cnt[*c].1 - 1);
+                        }
+                    }
+                    in_left.insert(v, !from_left);
+                    if from_left {
+                        num_left -= 1;
+                    } else {
+                        num_left += 1;
+                    }
+                    moved.insert(v);
+                }
+                _ => break,
+            }
+        }
+        vars.iter().cloned().partition(|v| in_left[v])
+    }
+
+    fn helper(
+        vars: &[VarLabel],
+        occurs: &Vec<Vec<usize>>,
+        num_clauses: usize,
+        leaf_size: usize,
+    ) -> VTree {
+        if vars.len() <= leaf_size {
+            BTree::Leaf(vars.to_vec())
+        } else {
+            let (l_s, r_s) = bisect(vars, occurs, num_clauses);
+            let l_tree = helper(&l_s, occurs, num_clauses, leaf_size);
+            let r_tree = helper(&r_s, occurs, num_clauses, leaf_size);
+            BTree::Node((), Box::new(l_tree), Box::new(r_tree))
+        }
+    }
+    helper(&vars, &occurs, cnf.clauses().len(), leaf_size)
+}
+
+/// A local change to a vtree, applied at the vtree node with the given
+/// depth-first index
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum VTreeMove {
+    RotateLeft(usize),
+    RotateRight(usize),
+    Swap(usize),
+}
+
+impl VTreeMove {
+    fn vnode(&self) -> usize {
+        match self {
+            &VTreeMove::RotateLeft(i) | &VTreeMove::RotateRight(i) | &VTreeMove::Swap(i) => i,
+        }
+    }
+
+    fn apply<N, L>(&self, t: &BTree<N, L>) -> Option<BTree<N, L>>
+    where
+        N: PartialEq + Eq + Clone,
+        L: PartialEq + Eq + Clone,
+    {
+        match self {
+            &VTreeMove::RotateLeft(i) => t.rotate_left(i),
+            &VTreeMove::RotateRight(i) => t.rotate_right(i),
+            &VTreeMove::Swap(i) => t.swap(i),
+        }
+    }
+}
+
+/// Budget for the vtree search performed by `SddManager::minimize`
+#[derive(Debug, Clone)]
+pub struct MinimizeOptions {
+    /// stop searching once this much time has passed
+    pub time_limit: Option<Duration>,
+    /// stop searching once the SDDs have at most this many nodes
+    pub size_limit: Option<usize>,
+    /// the maximum number of passes over the vtree
+    pub max_passes: usize,
+}
+
+impl MinimizeOptions {
+    pub fn new() -> MinimizeOptions {
+        MinimizeOptions::default()
+    }
+}
+
+impl Default for MinimizeOptions {
+    fn default() -> MinimizeOptions {
+        MinimizeOptions {
+            time_limit: None,
+            size_limit: None,
+            max_passes: 10,
+        }
+    }
+}
+
+/// Keys of the apply cache; each operation has its own entries, stored at the
+/// least common ancestor of its arguments' vtree nodes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum SddApply {
+    And(SddPtr, SddPtr),
+    Ite(SddPtr, SddPtr, SddPtr),
+    Xor(SddPtr, SddPtr),
+    Condition(SddPtr, VarLabel, bool),
+    Exists(SddPtr, VarLabel),
+    Compose(SddPtr, VarLabel, SddPtr),
+}
+
 pub struct SddManager {
     /// Managers ordered by their order in a depth-first left-first traversal of
     /// the vtree
@@ -103,8 +343,12 @@ pub struct SddManager {
     /// a helper structure which matches the vtree and is used for efficient LCA
     /// computation
     parent_ptr: Vec<(Option<usize>, usize)>,
+    /// for each vtree node, the range of depth-first indices spanned by its
+    /// subtree; used to check whether a variable lies below a node
+    vtree_range: Vec<(usize, usize)>,
     /// the apply cache
-    app_cache: Vec<Lru<(SddPtr, SddPtr), SddPtr>>,
+    app_cache: Vec<Lru<SddApply, SddPtr>>,
+    names: VarNames,
 }
 
 /// produces a vector of pointers to vtrees such that (i) the order is given by
@@ -133,6 +377,29 @@ fn into_parent_ptr_vec(vtree: &VTree) -> Vec<(Option<usize>, usize)> {
     helper(&vtree.into_order_tree(), 0, None)
 }
 
+/// produces a vector which gives, for each vtree node in depth-first order, the
+/// (inclusive) range of depth-first indices covered by its subtree
+fn into_range_vec(vtree: &VTree) -> Vec<(usize, usize)> {
+    fn helper(cur: &BTree<usize, usize>, v: &mut Vec<(usize, usize)>) -> (usize, usize) {
+        match cur {
+            &BTree::Leaf(idx) => {
+                v[idx] = (idx, idx);
+                (idx, idx)
+            }
+            &BTree::Node(idx, ref l, ref r) => {
+                let (lo, _) = helper(l, v);
+                let (_, hi) = helper(r, v);
+                v[idx] = (lo, hi);
+                (lo, hi)
+            }
+        }
+    }
+    let order_tree = vtree.into_order_tree();
+    let mut v = vec![(0, 0); order_tree.num_nodes()];
+    helper(&order_tree, &mut v);
+    v
+}
+
 /// find the index of the least common ancestor between `a` and `b`
 fn least_common_ancestor(
     parent_vec: &Vec<(Option<usize>, usize)>,
@@ -181,8 +448,10 @@ impl<'a> SddManager {
             tbl: SddTable::new(&vtree),
             stats: SddStats::new(),
             parent_ptr: into_parent_ptr_vec(&vtree),
+            vtree_range: into_range_vec(&vtree),
             vtree: vtree,
             app_cache: app_cache,
+            names: VarNames::new(),
         };
 
         return m;
@@ -265,17 +534,40 @@ impl<'a> SddManager {
         return self.vtree.in_order_iter().nth(f.vtree()).unwrap();
     }
 
+    /// The names of the manager's variables, used when printing and exporting
+    pub fn names(&self) -> &VarNames {
+        &self.names
+    }
+
+    pub fn names_mut(&mut self) -> &mut VarNames {
+        &mut self.names
+    }
+
     pub fn get_vtree_root(&self) -> &VTree {
         &self.vtree
     }
 
     /// get the BDD manager for `f`, where `f` is a BDD pointer
     /// panics if `f` is not a BDD pointer
-    fn get_bdd_mgr(&self, f: SddPtr) -> &BddManager {
+    pub fn get_bdd_mgr(&self, f: SddPtr) -> &BddManager {
         assert!(f.is_bdd());
         &self.tbl.bdd_man(f.vtree())
     }
 
+    /// Converts the label `lbl` of a BDD stored at the vtree leaf `vnode` into
+    /// an SDD variable label; BDD variables are labeled by their position in
+    /// the leaf
+    pub fn bdd_to_sdd_label(&self, vnode: usize, lbl: VarLabel) -> VarLabel {
+        self.tbl.bdd_conv(vnode)[&lbl]
+    }
+
+    /// The (prime, sub) elements of the decision node `f`, with any complement
+    /// of `f` pushed into the subs; panics if `f` is a constant or a BDD
+    pub fn elements(&self, f: SddPtr) -> Vec<(SddPtr, SddPtr)> {
+        assert!(!f.is_const() && !f.is_bdd());
+        self.expand(f, f.vtree())
+    }
+
     /// Compresses, trims, and canonicalizes the list of (prime, sub) terms and
     /// creates a new canonicalized term
     /// `node`: a list of (prime, sub) pairs
@@ -431,7 +723,7 @@ impl<'a> SddManager {
         let lca = least_common_ancestor(&self.parent_ptr, av, bv);
 
         // check if we have this application cached
-        let c = self.app_cache[lca].get((a, b));
+        let c = self.app_cache[lca].get(SddApply::And(a, b));
         if c.is_some() {
             return c.unwrap();
         }
@@ -522,7 +814,7 @@ impl<'a> SddManager {
                 // return a `true` SddPtr here, for trimming
                 if p.is_true() && s.is_true() {
                     let new_v = SddPtr::new_const(true);
-                    self.app_cache[lca].insert((a, b), new_v.clone());
+                    self.app_cache[lca].insert(SddApply::And(a, b), new_v.clone());
                     return new_v;
                 }
                 r.push((p, s));
@@ -537,7 +829,7 @@ impl<'a> SddManager {
 
         // canonicalize
         let ptr = self.compress(r, lca);
-        self.app_cache[lca].insert((a, b), ptr);
+        self.app_cache[lca].insert(SddApply::And(a, b), ptr);
         ptr
     }
 
@@ -549,78 +841,362 @@ impl<'a> SddManager {
         self.or_internal(a, b)
     }
 
+    /// true if the vtree node at index `vnode` has the leaf at index `leaf` in
+    /// its subtree
+    fn vtree_contains(&self, vnode: usize, leaf: usize) -> bool {
+        let (lo, hi) = self.vtree_range[vnode];
+        lo <= leaf && leaf <= hi
+    }
+
+    /// wrap a BDD pointer produced by the BDD manager at `vnode`, collapsing
+    /// constants to the canonical SDD constants
+    fn wrap_bdd(bdd: BddPtr, vnode: usize) -> SddPtr {
+        if bdd.is_true() {
+            SddPtr::new_const(true)
+        } else if bdd.is_false() {
+            SddPtr::new_const(false)
+        } else {
+            SddPtr::new_bdd(bdd, vnode as u16)
+        }
+    }
+
     /// Computes `f | var = value`
     pub fn condition(&mut self, f: SddPtr, lbl: VarLabel, value: bool) -> SddPtr {
+        let leaf = self.get_vtree_idx(lbl);
+        self.cond_helper(f, lbl, leaf, value)
+    }
+
+    fn cond_helper(&mut self, f: SddPtr, lbl: VarLabel, leaf: usize, value: bool) -> SddPtr {
         self.stats.num_rec += 1;
-        // TODO : this can bail out early by checking the vtree
-        // check base case
-        if f.is_const() {
+        // check base case: `f` does not mention `lbl` if it is not below
+        // `f`'s vtree node
+        if f.is_const() || !self.vtree_contains(f.vtree(), leaf) {
             return f;
         } else if f.is_bdd() {
-            // check if this BDD contains the label
-            if self.get_vtree(f).extract_leaf().contains(&lbl) {
-                // it does; condition and return
-                let mapped = self.tbl.sdd_to_bdd_label(&lbl).clone();
-                let bdd = self
-                    .tbl
-                    .bdd_man_mut(f.vtree())
-                    .condition(f.as_bdd_ptr(), mapped, value);
-                return SddPtr::new_bdd(bdd, f.vtree() as u16);
-            } else {
-                // do nothing
-                return f;
+            // this BDD contains the label; condition and return
+            let mapped = self.tbl.sdd_to_bdd_label(&lbl).clone();
+            let bdd = self
+                .tbl
+                .bdd_man_mut(f.vtree())
+                .condition(f.as_bdd_ptr(), mapped, value);
+            return SddManager::wrap_bdd(bdd, f.vtree());
+        };
+
+        // conditioning commutes with negation, so `f` and `!f` share an entry
+        let key = SddApply::Condition(f.regular(), lbl, value);
+        let r = match self.app_cache[f.vtree()].get(key) {
+            Some(r) => r,
+            None => {
+                let r = self.cond_node(f, lbl, leaf, value);
+                self.app_cache[f.vtree()].insert(key, r);
+                r
             }
         };
+        if f.is_compl() {
+            return r.neg();
+        } else {
+            return r;
+        }
+    }
 
+    /// Conditions the elements of the node `f`, ignoring its complement
+    fn cond_node(&mut self, f: SddPtr, lbl: VarLabel, leaf: usize, value: bool) -> SddPtr {
         let mut v = Vec::new();
         // f is a node; recurse and compress the result
         for (prime, sub) in self.tbl.sdd_get_or(f).to_vec().iter() {
-            let newp = self.condition(*prime, lbl, value);
-            let news = self.condition(*sub, lbl, value);
+            let newp = self.cond_helper(*prime, lbl, leaf, value);
+            // conditioning may falsify a prime; drop it to keep the node
+            // trimmed
+            if newp.is_false() {
+                continue;
+            }
+            let news = self.cond_helper(*sub, lbl, leaf, value);
             v.push((newp, news));
         }
-        let r = self.compress(v, f.vtree());
-        if f.is_compl() {
-            return r.neg();
+        self.compress(v, f.vtree())
+    }
+
+    /// Expands `f` into a list of (prime, sub) elements normalized for the
+    /// internal vtree node `lca`, which must be `f`'s vtree node or one of its
+    /// ancestors. Complemented nodes have the complement pushed into their
+    /// subs.
+    fn expand(&self, f: SddPtr, lca: usize) -> Vec<(SddPtr, SddPtr)> {
+        if f.is_const() {
+            vec![(SddPtr::new_const(true), f)]
+        } else if f.vtree() == lca {
+            self.tbl
+                .sdd_get_or(f)
+                .iter()
+                .map(|&(p, s)| (p, if f.is_compl() { s.neg() } else { s }))
+                .collect()
+        } else if is_prime(&self.vtree, f.vtree(), lca) {
+            // `f` lies in the left subtree, so it is a prime
+            vec![
+                (f, SddPtr::new_const(true)),
+                (f.neg(), SddPtr::new_const(false)),
+            ]
         } else {
-            return r;
+            vec![(SddPtr::new_const(true), f)]
         }
     }
 
     /// Computes the SDD representing the logical function `if f then g else h`
     pub fn ite(&mut self, f: SddPtr, g: SddPtr, h: SddPtr) -> SddPtr {
-        // TODO make this a primitive operation
-        let fg = self.and(f, g);
-        let negfh = self.and(f.neg(), h);
-        self.or(fg, negfh)
+        self.ite_rec(f, g, h)
+    }
+
+    fn ite_rec(&mut self, f: SddPtr, g: SddPtr, h: SddPtr) -> SddPtr {
+        self.stats.num_rec += 1;
+        // base cases
+        if f.is_true() || g == h {
+            return g;
+        } else if f.is_false() {
+            return h;
+        } else if g == f || g.is_true() {
+            return self.or_internal(f, h);
+        } else if g == f.neg() || g.is_false() {
+            return self.and_rec(f.neg(), h);
+        } else if h == f || h.is_false() {
+            return self.and_rec(f, g);
+        } else if h == f.neg() || h.is_true() {
+            return self.or_internal(f.neg(), g);
+        } else if g == h.neg() {
+            return self.xor_rec(f, h);
+        }
+
+        // normalize so that `f` and `g` are regular:
+        // ite(!f, g, h) = ite(f, h, g) and ite(f, !g, !h) = !ite(f, g, h)
+        let (f, g, h) = if f.is_compl() { (f.neg(), h, g) } else { (f, g, h) };
+        let (g, h, compl) = if g.is_compl() {
+            (g.neg(), h.neg(), true)
+        } else {
+            (g, h, false)
+        };
+
+        let same_leaf = f.is_bdd()
+            && g.is_bdd()
+            && h.is_bdd()
+            && f.vtree() == g.vtree()
+            && g.vtree() == h.vtree();
+        let r = if same_leaf {
+            let vnode = f.vtree();
+            let bdd = self.tbl.bdd_man_mut(vnode).ite(
+                f.as_bdd_ptr(),
+                g.as_bdd_ptr(),
+                h.as_bdd_ptr(),
+            );
+            SddManager::wrap_bdd(bdd, vnode)
+        } else {
+            let lca = least_common_ancestor(&self.parent_ptr, f.vtree(), g.vtree());
+            let lca = least_common_ancestor(&self.parent_ptr, lca, h.vtree());
+            let key = SddApply::Ite(f, g, h);
+            match self.app_cache[lca].get(key) {
+                Some(v) => return if compl { v.neg() } else { v },
+                None => (),
+            }
+            let f_v = self.expand(f, lca);
+            let g_v = self.expand(g, lca);
+            let h_v = self.expand(h, lca);
+            let mut r: Vec<(SddPtr, SddPtr)> = Vec::new();
+            for &(ref p1, ref s1) in f_v.iter() {
+                for &(ref p2, ref s2) in g_v.iter() {
+                    let p12 = self.and_rec(*p1, *p2);
+                    if p12.is_false() {
+                        continue;
+                    }
+                    for &(ref p3, ref s3) in h_v.iter() {
+                        let p = self.and_rec(p12, *p3);
+                        if p.is_false() {
+                            continue;
+                        }
+                        let s = self.ite_rec(*s1, *s2, *s3);
+                        r.push((p, s));
+                    }
+                }
+            }
+            let ptr = self.compress(r, lca);
+            self.app_cache[lca].insert(key, ptr);
+            ptr
+        };
+        if compl {
+            r.neg()
+        } else {
+            r
+        }
     }
 
     /// Computes the SDD representing the logical function `f <=> g`
     pub fn iff(&mut self, f: SddPtr, g: SddPtr) -> SddPtr {
-        self.ite(f, g, g.neg())
+        self.xor_rec(f, g).neg()
     }
 
     /// Computes the SDD representing the logical function `f xor g`
     pub fn xor(&mut self, f: SddPtr, g: SddPtr) -> SddPtr {
-        self.ite(f, g.neg(), g)
+        self.xor_rec(f, g)
+    }
+
+    fn xor_rec(&mut self, a: SddPtr, b: SddPtr) -> SddPtr {
+        self.stats.num_rec += 1;
+        // base cases
+        if a.is_false() {
+            return b;
+        } else if a.is_true() {
+            return b.neg();
+        } else if b.is_false() {
+            return a;
+        } else if b.is_true() {
+            return a.neg();
+        } else if a == b {
+            return SddPtr::new_const(false);
+        } else if a == b.neg() {
+            return SddPtr::new_const(true);
+        }
+
+        // push complements outward: !a xor b = !(a xor b)
+        let compl = a.is_compl() != b.is_compl();
+        // `regular` does not clear the complement flag of BDD pointers, so
+        // negate instead
+        let a = if a.is_compl() { a.neg() } else { a };
+        let b = if b.is_compl() { b.neg() } else { b };
+        let (a, b) = if a < b { (a, b) } else { (b, a) };
+
+        let r = if a.is_bdd() && b.is_bdd() && a.vtree() == b.vtree() {
+            let vnode = a.vtree();
+            let bdd = self
+                .tbl
+                .bdd_man_mut(vnode)
+                .xor(a.as_bdd_ptr(), b.as_bdd_ptr());
+            SddManager::wrap_bdd(bdd, vnode)
+        } else {
+            let lca = least_common_ancestor(&self.parent_ptr, a.vtree(), b.vtree());
+            let key = SddApply::Xor(a, b);
+            match self.app_cache[lca].get(key) {
+                Some(v) => return if compl { v.neg() } else { v },
+                None => (),
+            }
+            let a_v = self.expand(a, lca);
+            let b_v = self.expand(b, lca);
+            let mut r: Vec<(SddPtr, SddPtr)> = Vec::new();
+            for &(ref p1, ref s1) in a_v.iter() {
+                for &(ref p2, ref s2) in b_v.iter() {
+                    let p = self.and_rec(*p1, *p2);
+                    if p.is_false() {
+                        continue;
+                    }
+                    let s = self.xor_rec(*s1, *s2);
+                    r.push((p, s));
+                }
+            }
+            let ptr = self.compress(r, lca);
+            self.app_cache[lca].insert(key, ptr);
+            ptr
+        };
+        if compl {
+            r.neg()
+        } else {
+            r
+        }
     }
 
     /// Existentially quantifies out the variable `lbl` from `f`
     pub fn exists(&mut self, sdd: SddPtr, lbl: VarLabel) -> SddPtr {
-        // TODO this can be optimized by specializing it
-        let v1 = self.condition(sdd, lbl, true);
-        let v2 = self.condition(sdd, lbl, false);
-        self.or(v1, v2)
+        self.forget(sdd, &[lbl])
+    }
+
+    /// Existentially quantifies out every variable in `vars` from `f`. Only
+    /// the parts of `f` whose vtree contains one of `vars` are visited; since
+    /// the primes and sub of each element share no variables, quantification
+    /// distributes over them and each element is rebuilt by a single `and`.
+    pub fn forget(&mut self, f: SddPtr, vars: &[VarLabel]) -> SddPtr {
+        let leaves: Vec<(VarLabel, usize)> = vars
+            .iter()
+            .map(|lbl| (*lbl, self.get_vtree_idx(*lbl)))
+            .collect();
+        self.forget_h(f, &leaves, &mut HashMap::new())
+    }
+
+    fn forget_h(
+        &mut self,
+        f: SddPtr,
+        vars: &[(VarLabel, usize)],
+        cache: &mut HashMap<SddPtr, SddPtr>,
+    ) -> SddPtr {
+        self.stats.num_rec += 1;
+        if f.is_const() || !vars.iter().any(|(_, leaf)| self.vtree_contains(f.vtree(), *leaf)) {
+            return f;
+        }
+        // a single variable is quantified by `exists`, whose results are kept
+        // in the apply cache
+        let key = match vars {
+            &[(lbl, _)] => Some(SddApply::Exists(f, lbl)),
+            _ => None,
+        };
+        let cached = match key {
+            Some(key) => self.app_cache[f.vtree()].get(key),
+            None => cache.get(&f).cloned(),
+        };
+        match cached {
+            Some(v) => return v,
+            None => (),
+        }
+        let r = if f.is_bdd() {
+            let vnode = f.vtree();
+            let mut bdd = f.as_bdd_ptr();
+            for &(ref lbl, ref leaf) in vars.iter() {
+                if *leaf == vnode {
+                    let mapped = self.tbl.sdd_to_bdd_label(lbl).clone();
+                    bdd = self.tbl.bdd_man_mut(vnode).exists(bdd, mapped);
+                }
+            }
+            SddManager::wrap_bdd(bdd, vnode)
+        } else {
+            let mut r = SddPtr::new_const(false);
+            for &(ref p, ref s) in self.tbl.sdd_get_or(f).to_vec().iter() {
+                let s = if f.is_compl() { s.neg() } else { *s };
+                let new_p = self.forget_h(*p, vars, cache);
+                let new_s = self.forget_h(s, vars, cache);
+                let elem = self.and(new_p, new_s);
+                r = self.or(r, elem);
+                if r.is_true() {
+                    break;
+                }
+            }
+            r
+        };
+        match key {
+            Some(key) => self.app_cache[f.vtree()].insert(key, r),
+            None => {
+                cache.insert(f, r);
+            }
+        }
+        r
     }
 
     /// Compose `g` into `f` by substituting for `lbl`
     pub fn compose(&mut self, f: SddPtr, lbl: VarLabel, g: SddPtr) -> SddPtr {
         // TODO this can be optimized with a specialized implementation to make
         // it a single traversal
-        let var = self.var(lbl, true);
-        let iff = self.iff(var, g);
-        let a = self.and(iff, f);
-        self.exists(a, lbl)
+        if f.is_const() {
+            return f;
+        }
+        // the result lies below the vtree nodes of `f`, `g` and `lbl`
+        let lca = least_common_ancestor(&self.parent_ptr, f.vtree(), self.get_vtree_idx(lbl));
+        let lca = if g.is_const() {
+            lca
+        } else {
+            least_common_ancestor(&self.parent_ptr, lca, g.vtree())
+        };
+        let key = SddApply::Compose(f, lbl, g);
+        match self.app_cache[lca].get(key) {
+            Some(r) => return r,
+            None => (),
+        }
+        // f[g/lbl] = ite(g, f|lbl, f|!lbl), which holds even if g mentions lbl
+        let t = self.condition(f, lbl, true);
+        let e = self.condition(f, lbl, false);
+        let r = self.ite(g, t, e);
+        self.app_cache[lca].insert(key, r);
+        r
     }
 
     fn count_nodes_h(&self, f: SddPtr, sddcache: &mut HashSet<SddPtr>) -> u64 {
@@ -653,13 +1229,211 @@ impl<'a> SddManager {
         self.count_nodes_h(f, &mut HashSet::new())
     }
 
+    /// Counts the number of unique nodes shared among all of `roots`
+    pub fn count_nodes_lst(&self, roots: &[SddPtr]) -> u64 {
+        let mut cache = HashSet::new();
+        roots
+            .iter()
+            .fold(0, |acc, r| acc + self.count_nodes_h(*r, &mut cache))
+    }
+
+    /// Rebuilds `ptr`, a node from before a vtree change, under the current
+    /// vtree. Nodes whose subtable was kept are still valid and returned as-is;
+    /// BDD nodes only need their vtree index updated.
+    fn rebuild_node(
+        &mut self,
+        ptr: SddPtr,
+        old_to_new: &[usize],
+        detached: &DetachedSddNodes,
+        cache: &mut HashMap<SddPtr, SddPtr>,
+    ) -> SddPtr {
+        if ptr.is_const() {
+            return ptr;
+        }
+        if ptr.is_bdd() {
+            return SddPtr::new_bdd(ptr.as_bdd_ptr(), old_to_new[ptr.vtree()] as u16);
+        }
+        if !detached.contains(ptr.vtree()) {
+            assert_eq!(old_to_new[ptr.vtree()], ptr.vtree());
+            return ptr;
+        }
+        let r = match cache.get(&ptr.regular()) {
+            Some(v) => *v,
+            None => {
+                let mut r = SddPtr::new_const(false);
+                for &(ref p, ref s) in detached.sdd_get_or(ptr).to_vec().iter() {
+                    let new_p = self.rebuild_node(*p, old_to_new, detached, cache);
+                    let new_s = self.rebuild_node(*s, old_to_new, detached, cache);
+                    let elem = self.and(new_p, new_s);
+                    r = self.or(r, elem);
+                }
+                cache.insert(ptr.regular(), r);
+                r
+            }
+        };
+        if ptr.is_compl() {
+            r.neg()
+        } else {
+            r
+        }
+    }
+
+    /// Applies `mv` to the vtree and rewrites the SDD nodes it affects. Returns
+    /// the rewritten `roots` together with the move which undoes `mv`, or None
+    /// if `mv` does not apply to the current vtree.
+    fn vtree_move(&mut self, mv: VTreeMove, roots: &[SddPtr]) -> Option<(Vec<SddPtr>, VTreeMove)> {
+        fn label(t: &BTree<usize, usize>) -> usize {
+            match t {
+                &BTree::Leaf(v) => v,
+                &BTree::Node(v, _, _) => v,
+            }
+        }
+        let labels = self.vtree.into_order_tree();
+        let new_vtree = mv.apply(&self.vtree)?;
+        let new_labels = mv.apply(&labels).unwrap();
+        let new_to_old: Vec<usize> = new_labels.in_order_iter().map(label).collect();
+        let mut old_to_new = vec![0; new_to_old.len()];
+        for (new_idx, old_idx) in new_to_old.iter().enumerate() {
+            old_to_new[*old_idx] = new_idx;
+        }
+
+        // the SDD nodes to rebuild are those normalized for a vtree node
+        // changed by the move, or for one of its ancestors; all others are
+        // still canonical
+        let mut rebuild = vec![false; new_to_old.len()];
+        let (changed, inverse) = match (mv, labels.in_order_iter().nth(mv.vnode()).unwrap()) {
+            (VTreeMove::RotateLeft(i), &BTree::Node(_, _, ref r)) => {
+                (vec![i, label(r)], VTreeMove::RotateRight(label(r)))
+            }
+            (VTreeMove::RotateRight(i), &BTree::Node(_, ref l, _)) => {
+                (vec![i, label(l)], VTreeMove::RotateLeft(label(l)))
+            }
+            (VTreeMove::Swap(i), sub) => {
+                // every node below a swap changes its vtree index
+                let inner = sub
+                    .in_order_iter()
+                    .filter_map(|n| match n {
+                        &BTree::Node(v, _, _) => Some(v),
+                        _ => None,
+                    })
+                    .collect();
+                (inner, VTreeMove::Swap(old_to_new[i]))
+            }
+            _ => panic!("invalid vtree move"),
+        };
+        for idx in changed {
+            rebuild[idx] = true;
+        }
+        let mut cur = self.parent_ptr[mv.vnode()].0;
+        while let Some(p) = cur {
+            rebuild[p] = true;
+            cur = self.parent_ptr[p].0;
+        }
+
+        self.vtree = new_vtree;
+        self.parent_ptr = into_parent_ptr_vec(&self.vtree);
+        self.vtree_range = into_range_vec(&self.vtree);
+        let detached = self.tbl.reorder(&new_to_old, &rebuild);
+        let mut old_cache: Vec<Option<Lru<SddApply, SddPtr>>> =
+            self.app_cache.drain(..).map(Some).collect();
+        for old_idx in new_to_old.iter() {
+            let mut c = old_cache[*old_idx].take().unwrap();
+            if rebuild[*old_idx] {
+                c.clear();
+            }
+            self.app_cache.push(c);
+        }
+
+        let mut cache = HashMap::new();
+        let new_roots = roots
+            .iter()
+            .map(|r| self.rebuild_node(*r, &old_to_new, &detached, &mut cache))
+            .collect();
+        Some((new_roots, inverse))
+    }
+
+    /// Rotates the vtree node at (depth-first) index `vnode` to the left,
+    /// turning `(a, (b, c))` into `((a, b), c)`, and rewrites the SDD nodes
+    /// normalized for the changed vtree nodes and their ancestors. Returns the
+    /// equivalent of each of `roots` under the new vtree, or None if the right
+    /// child of `vnode` is a leaf. Vtree indices are preserved by rotations,
+    /// but pointers not passed in `roots` may be invalidated.
+    pub fn rotate_left(&mut self, vnode: usize, roots: &[SddPtr]) -> Option<Vec<SddPtr>> {
+        self.vtree_move(VTreeMove::RotateLeft(vnode), roots)
+            .map(|(r, _)| r)
+    }
+
+    /// Rotates the vtree node at index `vnode` to the right, turning
+    /// `((a, b), c)` into `(a, (b, c))`; see `rotate_left`. Returns None if the
+    /// left child of `vnode` is a leaf.
+    pub fn rotate_right(&mut self, vnode: usize, roots: &[SddPtr]) -> Option<Vec<SddPtr>> {
+        self.vtree_move(VTreeMove::RotateRight(vnode), roots)
+            .map(|(r, _)| r)
+    }
+
+    /// Swaps the children of the vtree node at index `vnode` and rewrites the
+    /// affected SDD nodes; see `rotate_left`. The vtree indices of all nodes
+    /// below `vnode` change, so any `SddWmc` must be rebuilt. Returns None if
+    /// `vnode` is a leaf.
+    pub fn swap(&mut self, vnode: usize, roots: &[SddPtr]) -> Option<Vec<SddPtr>> {
+        self.vtree_move(VTreeMove::Swap(vnode), roots).map(|(r, _)| r)
+    }
+
+    /// Searches for a vtree which shrinks `roots` by local search: passes are
+    /// made over every vtree node, trying a left rotation, right rotation and
+    /// swap at each, and a move is kept only if it reduces the total number of
+    /// nodes in `roots` (otherwise it is undone). Stops when a pass makes no
+    /// progress or the budget in `opts` is exhausted. Returns `roots` under
+    /// the final vtree; as with `rotate_left`, other pointers are invalidated.
+    pub fn minimize(&mut self, roots: &[SddPtr], opts: &MinimizeOptions) -> Vec<SddPtr> {
+        let start = Instant::now();
+        let mut roots = roots.to_vec();
+        let mut best = self.count_nodes_lst(&roots);
+        for _ in 0..opts.max_passes {
+            let mut improved = false;
+            for vnode in 0..self.parent_ptr.len() {
+                let moves = [
+                    VTreeMove::RotateLeft(vnode),
+                    VTreeMove::RotateRight(vnode),
+                    VTreeMove::Swap(vnode),
+                ];
+                for mv in moves.iter() {
+                    let out_of_time = opts.time_limit.map_or(false, |t| start.elapsed() >= t);
+                    let small_enough = opts.size_limit.map_or(false, |s| best <= s as u64);
+                    if out_of_time || small_enough {
+                        return roots;
+                    }
+                    let (new_roots, inverse) = match self.vtree_move(*mv, &roots) {
+                        Some(r) => r,
+                        None => continue,
+                    };
+                    let sz = self.count_nodes_lst(&new_roots);
+                    if sz < best {
+                        best = sz;
+                        roots = new_roots;
+                        improved = true;
+                    } else {
+                        roots = self.vtree_move(inverse, &new_roots).unwrap().0;
+                    }
+                }
+            }
+            if !improved {
+                break;
+            }
+        }
+        roots
+    }
+
     fn print_sdd_internal(&self, ptr: SddPtr) -> String {
         use pretty::*;
         fn helper(man: &SddManager, ptr: SddPtr) -> Doc<BoxDoc> {
             if ptr.is_bdd() {
                 let bdd_ptr = ptr.as_bdd_ptr();
                 let m = man.tbl.bdd_conv(ptr.vtree());
-                let s = man.tbl.bdd_man(ptr.vtree()).print_bdd_lbl(bdd_ptr, m);
+                let s = man
+                    .tbl
+                    .bdd_man(ptr.vtree())
+                    .print_bdd_lbl_named(bdd_ptr, m, &man.names);
                 Doc::from(s)
             } else {
                 if ptr.is_true() {
@@ -741,6 +1515,112 @@ impl<'a> SddManager {
         a == b
     }
 
+    /// Returns true if `f && g` is satisfiable. Decided by a short-circuiting
+    /// traversal, so no SDD nodes are allocated
+    pub fn intersects(&self, f: SddPtr, g: SddPtr) -> bool {
+        self.intersects_h(f, g, &mut HashMap::new())
+    }
+
+    /// `cache` holds the answers for the pairs which were fully decided
+    fn intersects_h(
+        &self,
+        a: SddPtr,
+        b: SddPtr,
+        cache: &mut HashMap<(SddPtr, SddPtr), bool>,
+    ) -> bool {
+        // every trimmed SDD other than false has a model
+        if a.is_false() || b.is_false() {
+            return false;
+        } else if a.is_true() || b.is_true() || a == b {
+            return true;
+        } else if a == b.neg() {
+            return false;
+        }
+        let (a, b) = if a < b { (a, b) } else { (b, a) };
+        if a.is_bdd() && b.is_bdd() && a.vtree() == b.vtree() {
+            return self
+                .tbl
+                .bdd_man(a.vtree())
+                .intersects(a.as_bdd_ptr(), b.as_bdd_ptr());
+        }
+        if let Some(r) = cache.get(&(a, b)) {
+            return *r;
+        }
+        // `a && b` is satisfiable iff some pair of elements has both
+        // satisfiable primes and satisfiable subs
+        let lca = least_common_ancestor(&self.parent_ptr, a.vtree(), b.vtree());
+        let a_v = self.expand(a, lca);
+        let b_v = self.expand(b, lca);
+        let r = a_v.iter().any(|&(p1, s1)| {
+            b_v.iter().any(|&(p2, s2)| {
+                self.intersects_h(s1, s2, cache) && self.intersects_h(p1, p2, cache)
+            })
+        });
+        cache.insert((a, b), r);
+        r
+    }
+
+    /// Returns true if `f` entails `g`, i.e. every model of `f` is a model of
+    /// `g`
+    pub fn implies(&self, f: SddPtr, g: SddPtr) -> bool {
+        !self.intersects(f, g.neg())
+    }
+
+    /// Returns true if `f` has a model which agrees with the partial
+    /// assignment `assgn`
+    pub fn sat_with(&self, f: SddPtr, assgn: &HashMap<VarLabel, bool>) -> bool {
+        fn helper(
+            man: &SddManager,
+            f: SddPtr,
+            leaf_assgn: &HashMap<usize, HashMap<VarLabel, bool>>,
+            cache: &mut HashMap<SddPtr, bool>,
+        ) -> bool {
+            if f.is_const() {
+                return f.is_true();
+            } else if f.is_bdd() {
+                return match leaf_assgn.get(&f.vtree()) {
+                    Some(a) => man.tbl.bdd_man(f.vtree()).sat_with(f.as_bdd_ptr(), a),
+                    None => true,
+                };
+            }
+            if let Some(r) = cache.get(&f) {
+                return *r;
+            }
+            let r = man.expand(f, f.vtree()).iter().any(|&(p, s)| {
+                helper(man, s, leaf_assgn, cache) && helper(man, p, leaf_assgn, cache)
+            });
+            cache.insert(f, r);
+            r
+        }
+        // split the assignment among the vtree leaves, relabeled for each
+        // leaf's BDD manager
+        let mut leaf_assgn: HashMap<usize, HashMap<VarLabel, bool>> = HashMap::new();
+        for (lbl, v) in assgn.iter() {
+            let leaf = self.get_vtree_idx(*lbl);
+            let mapped = self.tbl.sdd_to_bdd_label(lbl).clone();
+            leaf_assgn
+                .entry(leaf)
+                .or_insert_with(HashMap::new)
+                .insert(mapped, *v);
+        }
+        helper(self, f, &leaf_assgn, &mut HashMap::new())
+    }
+
+    /// Returns true if `f` entails the disjunction of the literals in `clause`
+    pub fn entails_clause(&self, f: SddPtr, clause: &[Literal]) -> bool {
+        // `f` entails the clause iff `f` has no model falsifying every literal
+        let mut assgn = HashMap::new();
+        for lit in clause.iter() {
+            let v = !lit.get_polarity();
+            match assgn.insert(lit.get_label(), v) {
+                // the clause contains both polarities of a variable
+                Some(prev) if prev != v => return true,
+                _ => (),
+            }
+        }
+        !self.sat_with(f, &assgn)
+    }
+
     pub fn is_true(&self, a: SddPtr) -> bool {
         a.is_true()
     }
@@ -749,6 +1629,31 @@ impl<'a> SddManager {
         a.is_false()
     }
 
+    /// Compile an SDD from an expression in the syntax of `BoolExpr::parse`,
+    /// whose variables are named by the manager's names
+    pub fn parse_expr(&mut self, s: &str) -> Result<SddPtr, ParseError> {
+        let names = &self.names;
+        let e = BoolExpr::parse_with(s, &mut |name| names.label(name).map(|l| l.value() as usize))?;
+        Ok(self.from_boolexpr(&e))
+    }
+
+    /// Compile an SDD for a pseudo-Boolean constraint, one layer per variable
+    pub fn from_pb(&mut self, c: &PbConstraint) -> SddPtr {
+        compile::pb::compile_pb(self, c)
+    }
+
+    /// Compile an SDD for the conjunction of the constraints of an OPB file
+    pub fn from_opb(&mut self, opb: &Opb) -> SddPtr {
+        compile::pb::compile_opb(self, opb)
+    }
+
+    /// Compile an SDD from a CNF, conjoining its clauses in the order given by
+    /// `opts`
+    pub fn from_cnf_with(&mut self, cnf: &Cnf, opts: &ScheduleOptions) -> SddPtr {
+        compile::schedule::compile_sdd(self, cnf, opts).0
+    }
+
+    /// Compile an SDD from a CNF; a CNF with no clauses is true
     pub fn from_cnf(&mut self, cnf: &Cnf) -> SddPtr {
         let mut cvec: Vec<SddPtr> = Vec::with_capacity(cnf.clauses().len());
         for lit_vec in cnf.clauses().iter() {
@@ -779,7 +1684,7 @@ impl<'a> SddManager {
                 }
             }
         }
-        helper(&cvec, self).unwrap()
+        helper(&cvec, self).unwrap_or(SddPtr::new_const(true))
     }
 
     pub fn from_boolexpr(&mut self, expr: &BoolExpr) -> SddPtr {
@@ -795,7 +1700,141 @@ impl<'a> SddManager {
                 let r2 = self.from_boolexpr(r);
                 self.or(r1, r2)
             }
+            &BoolExpr::Const(v) => SddPtr::new_const(v),
+            &BoolExpr::Not(ref e) => self.from_boolexpr(e).neg(),
+            &BoolExpr::Xor(ref l, ref r) => {
+                let r1 = self.from_boolexpr(l);
+                let r2 = self.from_boolexpr(r);
+                self.xor(r1, r2)
+            }
+            &BoolExpr::Iff(ref l, ref r) => {
+                let r1 = self.from_boolexpr(l);
+                let r2 = self.from_boolexpr(r);
+                self.iff(r1, r2)
+            }
+            &BoolExpr::Implies(ref l, ref r) => {
+                let r1 = self.from_boolexpr(l);
+                let r2 = self.from_boolexpr(r);
+                self.or(r1.neg(), r2)
+            }
+            &BoolExpr::Ite(ref c, ref t, ref e) => {
+                let r1 = self.from_boolexpr(c);
+                let r2 = self.from_boolexpr(t);
+                let r3 = self.from_boolexpr(e);
+                self.ite(r1, r2, r3)
+            }
+            &BoolExpr::Conj(ref v) => v.iter().fold(SddPtr::new_const(true), |acc, e| {
+                let r = self.from_boolexpr(e);
+                self.and(acc, r)
+            }),
+            &BoolExpr::Disj(ref v) => v.iter().fold(SddPtr::new_const(false), |acc, e| {
+                let r = self.from_boolexpr(e);
+                self.or(acc, r)
+            }),
+        }
+    }
+
+    /// Builds the SDD for the function represented by `ptr` in the BDD manager
+    /// `man`. Every variable of `ptr` must occur in this manager's vtree.
+    pub fn from_bdd(&mut self, man: &BddManager, ptr: BddPtr) -> SddPtr {
+        fn helper(
+            sdd: &mut SddManager,
+            man: &BddManager,
+            ptr: BddPtr,
+            cache: &mut HashMap<BddPtr, SddPtr>,
+        ) -> SddPtr {
+            if ptr.is_const() {
+                return SddPtr::new_const(ptr.is_true());
+            }
+            let reg = ptr.regular();
+            let r = match cache.get(&reg) {
+                Some(v) => *v,
+                None => {
+                    let l = helper(sdd, man, man.low(reg), cache);
+                    let h = helper(sdd, man, man.high(reg), cache);
+                    let v = sdd.var(man.topvar(reg), true);
+                    let r = sdd.ite(v, h, l);
+                    cache.insert(reg, r);
+                    r
+                }
+            };
+            if ptr.is_compl() {
+                r.neg()
+            } else {
+                r
+            }
+        }
+        helper(self, man, ptr, &mut HashMap::new())
+    }
+
+    /// Builds the BDD for `f` in the BDD manager `man`. The labels of the BDDs
+    /// at the vtree leaves are translated back into SDD variable labels, all
+    /// of which must be variables of `man`.
+    pub fn to_bdd(&self, f: SddPtr, man: &mut BddManager) -> BddPtr {
+        /// translates a BDD stored at the vtree leaf `vnode`
+        fn leaf_helper(
+            sdd: &SddManager,
+            vnode: usize,
+            ptr: BddPtr,
+            man: &mut BddManager,
+            cache: &mut HashMap<(usize, BddPtr), BddPtr>,
+        ) -> BddPtr {
+            if ptr.is_const() {
+                return ptr;
+            }
+            let reg = ptr.regular();
+            let r = match cache.get(&(vnode, reg)) {
+                Some(v) => *v,
+                None => {
+                    let src = sdd.tbl.bdd_man(vnode);
+                    let (lo, hi) = (src.low(reg), src.high(reg));
+                    let lbl = sdd.tbl.bdd_conv(vnode)[&src.topvar(reg)];
+                    let l = leaf_helper(sdd, vnode, lo, man, cache);
+                    let h = leaf_helper(sdd, vnode, hi, man, cache);
+                    let v = man.var(lbl, true);
+                    let r = man.ite(v, h, l);
+                    cache.insert((vnode, reg), r);
+                    r
+                }
+            };
+            if ptr.is_compl() {
+                r.neg()
+            } else {
+                r
+            }
+        }
+
+        fn helper(
+            sdd: &SddManager,
+            f: SddPtr,
+            man: &mut BddManager,
+            sdd_cache: &mut HashMap<SddPtr, BddPtr>,
+            bdd_cache: &mut HashMap<(usize, BddPtr), BddPtr>,
+        ) -> BddPtr {
+            if f.is_const() {
+                return if f.is_true() {
+                    man.true_ptr()
+                } else {
+                    man.false_ptr()
+                };
+            } else if f.is_bdd() {
+                return leaf_helper(sdd, f.vtree(), f.as_bdd_ptr(), man, bdd_cache);
+            }
+            match sdd_cache.get(&f) {
+                Some(v) => return *v,
+                None => (),
+            }
+            let mut r = man.false_ptr();
+            for &(ref p, ref s) in sdd.expand(f, f.vtree()).iter() {
+                let p = helper(sdd, *p, man, sdd_cache, bdd_cache);
+                let s = helper(sdd, *s, man, sdd_cache, bdd_cache);
+                let elem = man.and(p, s);
+                r = man.or(r, elem);
+            }
+            sdd_cache.insert(f, r);
+            r
         }
+        helper(self, f, man, &mut HashMap::new(), &mut HashMap::new())
     }
 
     pub fn get_stats(&self) -> &SddStats {
@@ -905,6 +1944,39 @@ fn sdd_test_exist() {
     );
 }
 
+#[test]
+fn sdd_empty_cnf() {
+    // regression: a CNF with no clauses used to panic
+    let mut man = SddManager::new(even_split(&vec![VarLabel::new(0), VarLabel::new(1)], 1));
+    let r = man.from_cnf(&Cnf::new(vec![]));
+    assert!(r.is_true());
+}
+
+#[test]
+fn sdd_op_cache_persists() {
+    let order: Vec<VarLabel> = (0..6).map(|v| VarLabel::new(v)).collect();
+    let mut man = SddManager::new(even_split(&order, 2));
+    let v: Vec<SddPtr> = order.iter().map(|l| man.var(*l, true)).collect();
+    let a = man.iff(v[0], v[4]);
+    let b = man.xor(v[1], v[5]);
+    let f = man.or(a, b);
+    let lbl = VarLabel::new(4);
+    let (c1, e1, p1) = (
+        man.condition(f, lbl, true),
+        man.exists(f, lbl),
+        man.compose(f, lbl, v[2]),
+    );
+    let calls = man.get_stats().num_rec;
+    // the second calls are answered by their first step
+    let (c2, e2, p2) = (
+        man.condition(f.neg(), lbl, true),
+        man.exists(f, lbl),
+        man.compose(f, lbl, v[2]),
+    );
+    assert_eq!(man.get_stats().num_rec, calls + 2);
+    assert_eq!((c2, e2, p2), (c1.neg(), e1, p1));
+}
+
 #[test]
 fn sdd_ite1() {
     let mut man = SddManager::new(even_split(
@@ -1050,12 +2122,40 @@ fn sdd_wmc2() {
     assert_eq!(man.unsmoothed_wmc(f, &wmc_map), 0.2*0.3 + 0.2*0.7 + 0.8*0.3);
 }
 
+#[test]
+fn test_linear_vtrees() {
+    let order: Vec<VarLabel> = (0..5).map(|x| VarLabel::new(x)).collect();
+    let leaves = |t: &VTree| -> Vec<Vec<VarLabel>> {
+        t.in_order_iter()
+            .filter_map(|n| match n {
+                &BTree::Leaf(ref l) => Some(l.clone()),
+                _ => None,
+            })
+            .collect()
+    };
+    let right = right_linear(&order, 2);
+    assert_eq!(leaves(&right), vec![order[0..2].to_vec(), order[2..4].to_vec(), order[4..].to_vec()]);
+    match right {
+        BTree::Node(_, ref l, _) => assert_eq!(l.extract_leaf(), &order[0..2].to_vec()),
+        _ => panic!("expected a node"),
+    }
+    let left = left_linear(&order, 2);
+    assert_eq!(leaves(&left), vec![order[0..1].to_vec(), order[1..3].to_vec(), order[3..].to_vec()]);
+    match left {
+        BTree::Node(_, _, ref r) => assert_eq!(r.extract_leaf(), &order[3..].to_vec()),
+        _ => panic!("expected a node"),
+    }
+}
+
 #[cfg(test)]
 mod test_sdd_manager {
     use repr::cnf::Cnf;
+    use repr::boolexpr::BoolExpr;
+    use repr::pb::PbConstraint;
     use manager::rsbdd_manager::{BddManager, BddWmc};
     use repr::var_label::{VarLabel, Literal};
     use quickcheck::TestResult;
+    use rand::{SeedableRng, StdRng};
     use std::collections::HashMap;
     use std::iter::FromIterator;
 
@@ -1074,6 +2174,42 @@ mod test_sdd_manager {
       }
   }
 
+  quickcheck! {
+      fn forget_eq_cond(c: Cnf, vars: Vec<u8>) -> bool {
+          let order : Vec<VarLabel> = (0..16).map(|x| VarLabel::new(x)).collect();
+          let mut mgr = super::SddManager::new(super::even_split(&order, 3));
+          let cnf = mgr.from_cnf(&c);
+          let vars: Vec<VarLabel> = vars.iter().take(4).map(|v| VarLabel::new((*v % 16) as u64)).collect();
+          let forgot = mgr.forget(cnf, &vars);
+          let mut expected = cnf;
+          for v in vars.iter() {
+              let t = mgr.condition(expected, *v, true);
+              let f = mgr.condition(expected, *v, false);
+              expected = mgr.or(t, f);
+          }
+          forgot == expected
+      }
+  }
+
+  quickcheck! {
+      fn ops_repeat(c: Cnf, v: u8, g: Cnf) -> bool {
+          let order : Vec<VarLabel> = (0..16).map(|x| VarLabel::new(x)).collect();
+          let mut mgr = super::SddManager::new(super::even_split(&order, 3));
+          let v = VarLabel::new(v as u64 % 16);
+          let (f, g) = (mgr.from_cnf(&c), mgr.from_cnf(&g));
+          // each operation twice, so that the second one may be cached
+          let ops: Vec<_> = (0..2).map(|_| {
+              (mgr.condition(f.neg(), v, true), mgr.exists(f.neg(), v), mgr.compose(f, v, g))
+          }).collect();
+          let (cond, ex, comp) = ops[0];
+          let (t, e) = (mgr.condition(f, v, true), mgr.condition(f, v, false));
+          let ex_expected = mgr.or(t.neg(), e.neg());
+          let comp_expected = mgr.ite(g, t, e);
+          ops[0] == ops[1] && mgr.sdd_eq(cond, t.neg()) && mgr.sdd_eq(ex, ex_expected)
+              && mgr.sdd_eq(comp, comp_expected)
+      }
+  }
+
   quickcheck! {
       fn ite_iff(c1: Cnf, c2: Cnf) -> bool {
           let order : Vec<VarLabel> = (0..16).map(|x| VarLabel::new(x)).collect();
@@ -1091,6 +2227,124 @@ mod test_sdd_manager {
   }
 
 
+  quickcheck! {
+      fn ite_xor_eq_apply(c1: Cnf, c2: Cnf, c3: Cnf) -> bool {
+          let order : Vec<VarLabel> = (0..16).map(|x| VarLabel::new(x)).collect();
+          let mut mgr = super::SddManager::new(super::even_split(&order, 3));
+          let f = mgr.from_cnf(&c1);
+          let g = mgr.from_cnf(&c2);
+          let h = mgr.from_cnf(&c3);
+          let ite = mgr.ite(f, g, h);
+          let fg = mgr.and(f, g);
+          let nfh = mgr.and(f.neg(), h);
+          let expected_ite = mgr.or(fg, nfh);
+
+          let xor = mgr.xor(f, g);
+          let either = mgr.or(f, g);
+          let not_both = mgr.or(f.neg(), g.neg());
+          let expected_xor = mgr.and(either, not_both);
+          ite == expected_ite && xor == expected_xor
+      }
+  }
+
+  quickcheck! {
+      fn implies_eq_apply(c1: Cnf, c2: Cnf) -> bool {
+          let order : Vec<VarLabel> = (0..16).map(|x| VarLabel::new(x)).collect();
+          let mut mgr = super::SddManager::new(super::even_split(&order, 3));
+          let f = mgr.from_cnf(&c1);
+          let g = mgr.from_cnf(&c2);
+          let f_and_g = mgr.and(f, g);
+          let f_and_not_g = mgr.and(f, g.neg());
+          let entails = c2.clauses().iter().all(|clause| {
+              let c = mgr.from_cnf(&Cnf::new(vec![clause.clone()]));
+              mgr.entails_clause(f, clause) == mgr.implies(f, c)
+          });
+          mgr.intersects(f, g) == !f_and_g.is_false()
+              && mgr.implies(f, g) == f_and_not_g.is_false()
+              && mgr.implies(f_and_g, g)
+              && entails
+      }
+  }
+
+  quickcheck! {
+      fn intersects_eq_and(c1: Cnf, c2: Cnf, vtree: u8) -> bool {
+          let order : Vec<VarLabel> = (0..8).map(|x| VarLabel::new(x)).collect();
+          let vtree = match vtree % 3 {
+              0 => super::even_split(&order, 2),
+              1 => super::right_linear(&order, 1),
+              _ => super::left_linear(&order, 1),
+          };
+          let mut mgr = super::SddManager::new(vtree);
+          let f = mgr.from_cnf(&c1);
+          let g = mgr.from_cnf(&c2);
+          let f_and_g = mgr.and(f, g);
+          let f_and_not_g = mgr.and(f, g.neg());
+          let entails = c2.clauses().iter().all(|clause| {
+              let c = mgr.from_cnf(&Cnf::new(vec![clause.clone()]));
+              let f_and_not_c = mgr.and(f, c.neg());
+              mgr.entails_clause(f, clause) == f_and_not_c.is_false()
+          });
+          mgr.intersects(f, g) == !f_and_g.is_false()
+              && mgr.implies(f, g) == f_and_not_g.is_false()
+              && entails
+      }
+  }
+
+  quickcheck! {
+      fn bdd_conversion_roundtrip(c: Cnf) -> bool {
+          let order : Vec<VarLabel> = (0..16).map(|x| VarLabel::new(x)).collect();
+          let mut mgr = super::SddManager::new(super::even_split(&order, 3));
+          let mut bdd_mgr = BddManager::new_default_order(16);
+          let sdd = mgr.from_cnf(&c);
+          let bdd = bdd_mgr.from_cnf(&c);
+          let to = mgr.to_bdd(sdd, &mut bdd_mgr);
+          let from = mgr.from_bdd(&bdd_mgr, bdd);
+          to == bdd && from == sdd
+      }
+  }
+
+  quickcheck! {
+      fn from_boolexpr_eval(e: BoolExpr) -> bool {
+          let order : Vec<VarLabel> = (0..6).map(|x| VarLabel::new(x)).collect();
+          let mut mgr = super::SddManager::new(super::even_split(&order, 2));
+          let f = mgr.from_boolexpr(&e);
+          (0..64).all(|bits: u64| {
+              let assgn: HashMap<VarLabel, bool> = HashMap::from_iter(
+                  (0..6).map(|v| (VarLabel::new(v), bits & (1 << v) != 0)));
+              mgr.eval_sdd(f, &assgn) == e.eval(&assgn)
+          })
+      }
+  }
+
+  #[test]
+  fn parse_named() {
+      let order : Vec<VarLabel> = (0..4).map(|x| VarLabel::new(x)).collect();
+      let mut mgr = super::SddManager::new(super::even_split(&order, 1));
+      mgr.names_mut().insert(VarLabel::new(0), "a");
+      mgr.names_mut().insert(VarLabel::new(3), "b");
+      let f = mgr.parse_expr("a -> b").unwrap();
+      let a = mgr.var(VarLabel::new(0), false);
+      let b = mgr.var(VarLabel::new(3), true);
+      let expected = mgr.or(a, b);
+      assert!(mgr.sdd_eq(f, expected));
+      assert!(mgr.print_sdd(f).contains("b"));
+      let err = mgr.parse_expr("a | c").unwrap_err();
+      assert_eq!(err.msg, "column 5: unknown variable `c`");
+  }
+
+  quickcheck! {
+      fn from_pb_eval(c: PbConstraint) -> bool {
+          let order : Vec<VarLabel> = (0..6).map(|x| VarLabel::new(x)).collect();
+          let mut mgr = super::SddManager::new(super::even_split(&order, 2));
+          let f = mgr.from_pb(&c);
+          (0..64).all(|bits: u64| {
+              let assgn: HashMap<VarLabel, bool> = HashMap::from_iter(
+                  (0..6).map(|v| (VarLabel::new(v), bits & (1 << v) != 0)));
+              mgr.eval_sdd(f, &assgn) == c.eval(&assgn)
+          })
+      }
+  }
+
   quickcheck! {
       fn sdd_wmc_eq(clauses: Vec<Vec<Literal>>) -> TestResult {
 
@@ -1116,6 +2370,72 @@ mod test_sdd_manager {
       }
   }
 
+  quickcheck! {
+      fn vtree_builders_wmc_eq(c: Cnf, seed: usize, leaf_size: usize) -> TestResult {
+          let leaf_size = leaf_size % 4 + 1;
+          let weight_map : HashMap<VarLabel, (f64, f64)> = HashMap::from_iter(
+              (0..c.num_vars()).map(|x| (VarLabel::new(x as u64), (0.5, 0.5))));
+          let mut bddmgr = BddManager::new_default_order(c.num_vars());
+          let cnf_bdd = bddmgr.from_cnf(&c);
+          let bdd_res = bddmgr.wmc(cnf_bdd, &BddWmc::new_with_default(0.0, 1.0, weight_map.clone()));
+
+          let order : Vec<VarLabel> = (0..c.num_vars()).map(|x| VarLabel::new(x as u64)).collect();
+          let mut rng: StdRng = SeedableRng::from_seed(&[seed][..]);
+          let vtrees = vec![
+              super::right_linear(&order, leaf_size),
+              super::left_linear(&order, leaf_size),
+              super::rand_vtree(&mut rng, &order, leaf_size),
+              super::cnf_vtree(&c, leaf_size),
+          ];
+          for vtree in vtrees {
+              let mut mgr = super::SddManager::new(vtree);
+              let cnf_sdd = mgr.from_cnf(&c);
+              let sdd_wmc = super::SddWmc::new_with_default(0.0, 1.0, &mut mgr, &weight_map);
+              if mgr.unsmoothed_wmc(cnf_sdd, &sdd_wmc) != bdd_res {
+                  return TestResult::failed();
+              }
+          }
+          TestResult::passed()
+      }
+  }
+
+  quickcheck! {
+      fn vtree_moves_canonical(c1: Cnf, c2: Cnf, seed: usize, moves: Vec<(u8, usize)>) -> bool {
+          let order : Vec<VarLabel> = (0..8).map(|x| VarLabel::new(x)).collect();
+          let mut rng: StdRng = SeedableRng::from_seed(&[seed][..]);
+          let vtree = super::rand_vtree(&mut rng, &order, 2);
+          let num_vnodes = vtree.num_nodes();
+          let mut mgr = super::SddManager::new(vtree);
+          let mut roots = vec![mgr.from_cnf(&c1), mgr.from_cnf(&c2)];
+          for (op, vnode) in moves.into_iter().take(8) {
+              let vnode = vnode % num_vnodes;
+              let r = match op % 3 {
+                  0 => mgr.rotate_left(vnode, &roots),
+                  1 => mgr.rotate_right(vnode, &roots),
+                  _ => mgr.swap(vnode, &roots),
+              };
+              if let Some(r) = r {
+                  roots = r;
+              }
+          }
+          // the rewritten SDDs must be canonical for the final vtree
+          let fresh1 = mgr.from_cnf(&c1);
+          let fresh2 = mgr.from_cnf(&c2);
+          roots[0] == fresh1 && roots[1] == fresh2
+      }
+  }
+
+  quickcheck! {
+      fn minimize_shrinks(c: Cnf) -> bool {
+          let order : Vec<VarLabel> = (0..8).map(|x| VarLabel::new(x)).collect();
+          let mut mgr = super::SddManager::new(super::right_linear(&order, 1));
+          let root = mgr.from_cnf(&c);
+          let before = mgr.count_nodes(root);
+          let r = mgr.minimize(&[root], &super::MinimizeOptions::new());
+          mgr.count_nodes(r[0]) <= before && r[0] == mgr.from_cnf(&c)
+      }
+  }
+
 
 }
 
@@ -1,15 +1,32 @@
-use dimacs::*;
+use dimacs::{parse_dimacs, Instance, Sign};
 use rand;
 use rand::distributions::IndependentSample;
 use rand::StdRng;
-use repr::var_label::VarLabel;
+use repr::cnf::Cnf;
+use repr::var_label::{Literal, VarLabel};
+use serialize::ParseError;
 use std::collections::{HashMap, HashSet};
+use std::fmt;
+extern crate quickcheck;
+use self::quickcheck::{Arbitrary, Gen};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum BoolExpr {
+    /// a literal: a variable and its polarity
     Var(usize, bool),
+    Const(bool),
+    Not(Box<BoolExpr>),
     And(Box<BoolExpr>, Box<BoolExpr>),
     Or(Box<BoolExpr>, Box<BoolExpr>),
+    Xor(Box<BoolExpr>, Box<BoolExpr>),
+    Iff(Box<BoolExpr>, Box<BoolExpr>),
+    Implies(Box<BoolExpr>, Box<BoolExpr>),
+    /// `Ite(c, t, e)` is `t` if `c` holds and `e` otherwise
+    Ite(Box<BoolExpr>, Box<BoolExpr>, Box<BoolExpr>),
+    /// an n-ary conjunction; the empty conjunction is true
+    Conj(Vec<BoolExpr>),
+    /// an n-ary disjunction; the empty disjunction is false
+    Disj(Vec<BoolExpr>),
 }
 
 impl BoolExpr {
@@ -122,6 +139,20 @@ impl BoolExpr {
                 let r_v = (*r).eval(values);
                 l_v || r_v
             }
+            &BoolExpr::Const(v) => v,
+            &BoolExpr::Not(ref e) => !e.eval(values),
+            &BoolExpr::Xor(ref l, ref r) => l.eval(values) != r.eval(values),
+            &BoolExpr::Iff(ref l, ref r) => l.eval(values) == r.eval(values),
+            &BoolExpr::Implies(ref l, ref r) => !l.eval(values) || r.eval(values),
+            &BoolExpr::Ite(ref c, ref t, ref e) => {
+                if c.eval(values) {
+                    t.eval(values)
+                } else {
+                    e.eval(values)
+                }
+            }
+            &BoolExpr::Conj(ref v) => v.iter().all(|e| e.eval(values)),
+            &BoolExpr::Disj(ref v) => v.iter().any(|e| e.eval(values)),
         }
     }
 
@@ -131,10 +162,26 @@ impl BoolExpr {
                 &BoolExpr::Var(lbl, _) => {
                     cur_set.insert(lbl);
                 }
-                &BoolExpr::And(ref l, ref r) | &BoolExpr::Or(ref l, ref r) => {
+                &BoolExpr::Const(_) => (),
+                &BoolExpr::Not(ref e) => traverse(e, cur_set),
+                &BoolExpr::And(ref l, ref r)
+                | &BoolExpr::Or(ref l, ref r)
+                | &BoolExpr::Xor(ref l, ref r)
+                | &BoolExpr::Iff(ref l, ref r)
+                | &BoolExpr::Implies(ref l, ref r) => {
                     traverse(l, cur_set);
                     traverse(r, cur_set);
                 }
+                &BoolExpr::Ite(ref c, ref t, ref e) => {
+                    traverse(c, cur_set);
+                    traverse(t, cur_set);
+                    traverse(e, cur_set);
+                }
+                &BoolExpr::Conj(ref v) | &BoolExpr::Disj(ref v) => {
+                    for e in v.iter() {
+                        traverse(e, cur_set);
+                    }
+                }
             }
         }
         let mut r = HashSet::new();
@@ -142,3 +189,714 @@ impl BoolExpr {
         r
     }
 }
+
+/// The negation of `l`
+fn negate(l: Literal) -> Literal {
+    Literal::new(l.get_label(), !l.get_polarity())
+}
+
+/// Disjoins two sets of clauses by distribution, dropping tautologies; None if
+/// the result has more than `max` clauses
+fn distribute(
+    a: &[Vec<Literal>],
+    b: &[Vec<Literal>],
+    max: usize,
+) -> Option<Vec<Vec<Literal>>> {
+    let mut r: Vec<Vec<Literal>> = Vec::new();
+    for ca in a.iter() {
+        for cb in b.iter() {
+            let mut c = ca.clone();
+            for l in cb.iter() {
+                if !c.contains(l) {
+                    c.push(*l);
+                }
+            }
+            if c.iter().any(|l| c.contains(&negate(*l))) || r.contains(&c) {
+                continue;
+            }
+            r.push(c);
+            if r.len() > max {
+                return None;
+            }
+        }
+    }
+    Some(r)
+}
+
+/// The clauses of `e` if `polarity` is true and of its negation otherwise; an
+/// empty clause is false
+fn cnf_clauses(e: &BoolExpr, polarity: bool, max: usize) -> Option<Vec<Vec<Literal>>> {
+    use self::BoolExpr::*;
+    let b = |e: &BoolExpr| Box::new(e.clone());
+    let n = |e: &BoolExpr| Box::new(Not(Box::new(e.clone())));
+    // the operands of a conjunction (if `conj` is set) or a disjunction
+    let (conj, operands): (bool, Vec<&BoolExpr>) = match e {
+        &Var(lbl, p) => {
+            return Some(vec![vec![Literal::new(VarLabel::new(lbl as u64), p == polarity)]]);
+        }
+        &Const(v) => return Some(if v == polarity { vec![] } else { vec![vec![]] }),
+        &Not(ref e) => return cnf_clauses(e, !polarity, max),
+        &Implies(ref l, ref r) => return cnf_clauses(&Or(n(l), b(r)), polarity, max),
+        &Xor(ref l, ref r) => {
+            let e = And(Box::new(Or(b(l), b(r))), Box::new(Or(n(l), n(r))));
+            return cnf_clauses(&e, polarity, max);
+        }
+        &Iff(ref l, ref r) => return cnf_clauses(&Xor(b(l), b(r)), !polarity, max),
+        &Ite(ref c, ref t, ref f) => {
+            let e = And(Box::new(Or(n(c), b(t))), Box::new(Or(b(c), b(f))));
+            return cnf_clauses(&e, polarity, max);
+        }
+        &And(ref l, ref r) => (polarity, vec![&**l, &**r]),
+        &Or(ref l, ref r) => (!polarity, vec![&**l, &**r]),
+        &Conj(ref v) => (polarity, v.iter().collect()),
+        &Disj(ref v) => (!polarity, v.iter().collect()),
+    };
+    let mut r: Vec<Vec<Literal>> = if conj { vec![] } else { vec![vec![]] };
+    for o in operands.into_iter() {
+        let c = cnf_clauses(o, polarity, max)?;
+        if conj {
+            r.extend(c.into_iter());
+            if r.len() > max {
+                return None;
+            }
+        } else {
+            r = distribute(&r, &c, max)?;
+        }
+    }
+    Some(r)
+}
+
+/// Builds a Tseitin encoding of an expression, allocating auxiliary variables
+/// from `next`
+struct Tseitin {
+    clauses: Vec<Vec<Literal>>,
+    next: usize,
+    aux: Vec<VarLabel>,
+    /// an auxiliary variable which is forced to be true, if one is needed
+    true_lit: Option<Literal>,
+}
+
+impl Tseitin {
+    fn fresh(&mut self) -> Literal {
+        let lbl = VarLabel::new(self.next as u64);
+        self.next += 1;
+        self.aux.push(lbl);
+        Literal::new(lbl, true)
+    }
+
+    fn constant(&mut self, v: bool) -> Literal {
+        let t = match self.true_lit {
+            Some(t) => t,
+            None => {
+                let t = self.fresh();
+                self.clauses.push(vec![t]);
+                self.true_lit = Some(t);
+                t
+            }
+        };
+        if v {
+            t
+        } else {
+            negate(t)
+        }
+    }
+
+    /// a literal equivalent to the conjunction of `lits`
+    fn and(&mut self, lits: Vec<Literal>) -> Literal {
+        if lits.is_empty() {
+            return self.constant(true);
+        } else if lits.len() == 1 {
+            return lits[0];
+        }
+        let a = self.fresh();
+        let mut long = vec![a];
+        for l in lits.into_iter() {
+            self.clauses.push(vec![negate(a), l]);
+            long.push(negate(l));
+        }
+        self.clauses.push(long);
+        a
+    }
+
+    /// a literal equivalent to `l` xor `r`
+    fn xor(&mut self, l: Literal, r: Literal) -> Literal {
+        let a = self.fresh();
+        let (na, nl, nr) = (negate(a), negate(l), negate(r));
+        self.clauses.push(vec![na, l, r]);
+        self.clauses.push(vec![na, nl, nr]);
+        self.clauses.push(vec![a, nl, r]);
+        self.clauses.push(vec![a, l, nr]);
+        a
+    }
+
+    /// a literal equivalent to `if c then t else e`
+    fn ite(&mut self, c: Literal, t: Literal, e: Literal) -> Literal {
+        let a = self.fresh();
+        let (na, nc) = (negate(a), negate(c));
+        self.clauses.push(vec![na, nc, t]);
+        self.clauses.push(vec![na, c, e]);
+        self.clauses.push(vec![a, nc, negate(t)]);
+        self.clauses.push(vec![a, c, negate(e)]);
+        a
+    }
+
+    fn encode(&mut self, e: &BoolExpr) -> Literal {
+        use self::BoolExpr::*;
+        match e {
+            &Var(lbl, p) => Literal::new(VarLabel::new(lbl as u64), p),
+            &Const(v) => self.constant(v),
+            &Not(ref e) => negate(self.encode(e)),
+            &And(ref l, ref r) => {
+                let lits = vec![self.encode(l), self.encode(r)];
+                self.and(lits)
+            }
+            &Or(ref l, ref r) => {
+                let lits = vec![negate(self.encode(l)), negate(self.encode(r))];
+                negate(self.and(lits))
+            }
+            &Conj(ref v) => {
+                let lits = v.iter().map(|e| self.encode(e)).collect();
+                self.and(lits)
+            }
+            &Disj(ref v) => {
+                let lits = v.iter().map(|e| negate(self.encode(e))).collect();
+                negate(self.and(lits))
+            }
+            &Implies(ref l, ref r) => {
+                let lits = vec![self.encode(l), negate(self.encode(r))];
+                negate(self.and(lits))
+            }
+            &Xor(ref l, ref r) => {
+                let (l, r) = (self.encode(l), self.encode(r));
+                self.xor(l, r)
+            }
+            &Iff(ref l, ref r) => {
+                let (l, r) = (self.encode(l), self.encode(r));
+                negate(self.xor(l, r))
+            }
+            &Ite(ref c, ref t, ref f) => {
+                let (c, t, f) = (self.encode(c), self.encode(t), self.encode(f));
+                self.ite(c, t, f)
+            }
+        }
+    }
+}
+
+impl BoolExpr {
+    /// Converts the expression into an equivalent CNF by distributing
+    /// disjunctions over conjunctions. The CNF can be exponentially larger than
+    /// the expression, so this returns None if it would have more than
+    /// `max_clauses` clauses.
+    ///
+    /// `Cnf` cannot hold an empty clause, so an unsatisfiable expression is
+    /// converted into the clauses `x0` and `!x0`.
+    pub fn to_cnf(&self, max_clauses: usize) -> Option<Cnf> {
+        let clauses = cnf_clauses(self, true, max_clauses)?;
+        if clauses.iter().any(|c| c.is_empty()) {
+            let x0 = Literal::new(VarLabel::new(0), true);
+            return Some(Cnf::new(vec![vec![x0], vec![negate(x0)]]));
+        }
+        Some(Cnf::new(clauses))
+    }
+
+    /// Builds the Tseitin encoding of the expression: an equisatisfiable CNF
+    /// of linear size which introduces an auxiliary variable for each
+    /// connective. Auxiliary variables are numbered from `num_vars`, which
+    /// must exceed every variable of the expression, and are returned along
+    /// with the CNF. Each model of the expression extends to exactly one model
+    /// of the CNF, so the two have the same model count.
+    pub fn tseitin(&self, num_vars: usize) -> (Cnf, Vec<VarLabel>) {
+        assert!(
+            self.varset().iter().all(|v| *v < num_vars),
+            "auxiliary variables would overlap the expression's variables"
+        );
+        let mut t = Tseitin {
+            clauses: Vec::new(),
+            next: num_vars,
+            aux: Vec::new(),
+            true_lit: None,
+        };
+        let root = t.encode(self);
+        t.clauses.push(vec![root]);
+        (Cnf::new(t.clauses), t.aux)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    LParen,
+    RParen,
+    Comma,
+    Not,
+    And,
+    Or,
+    Xor,
+    Implies,
+    Iff,
+    Ident(String),
+    End,
+}
+
+/// Splits `s` into tokens, each with its (1-based) line and column
+fn tokenize(s: &str) -> Result<Vec<(Token, usize, usize)>, ParseError> {
+    let chars: Vec<char> = s.chars().collect();
+    let mut r = Vec::new();
+    let (mut line, mut col) = (1, 1);
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let rest: String = chars[i..].iter().take(3).collect();
+        let (tok, len) = if c == '\n' {
+            line += 1;
+            col = 1;
+            i += 1;
+            continue;
+        } else if c.is_whitespace() {
+            (None, 1)
+        } else if rest.starts_with("<->") || rest.starts_with("<=>") {
+            (Some(Token::Iff), 3)
+        } else if rest.starts_with("->") || rest.starts_with("=>") {
+            (Some(Token::Implies), 2)
+        } else if rest.starts_with("&&") {
+            (Some(Token::And), 2)
+        } else if rest.starts_with("||") {
+            (Some(Token::Or), 2)
+        } else if c.is_alphabetic() || c == '_' {
+            let ident: String = chars[i..]
+                .iter()
+                .take_while(|c| c.is_alphanumeric() || **c == '_')
+                .collect();
+            let len = ident.chars().count();
+            (Some(Token::Ident(ident)), len)
+        } else {
+            let tok = match c {
+                '(' => Token::LParen,
+                ')' => Token::RParen,
+                ',' => Token::Comma,
+                '!' | '~' => Token::Not,
+                '&' => Token::And,
+                '|' => Token::Or,
+                '^' => Token::Xor,
+                _ => {
+                    let msg = format!("column {}: unexpected character `{}`", col, c);
+                    return Err(ParseError::new(line, &msg));
+                }
+            };
+            (Some(tok), 1)
+        };
+        match tok {
+            Some(t) => r.push((t, line, col)),
+            None => (),
+        }
+        i += len;
+        col += len;
+    }
+    r.push((Token::End, line, col));
+    Ok(r)
+}
+
+/// A recursive-descent parser for infix Boolean expressions
+struct Parser<'a> {
+    tokens: Vec<(Token, usize, usize)>,
+    pos: usize,
+    /// the index of each variable name, or None if it is unknown
+    lookup: &'a mut dyn FnMut(&str) -> Option<usize>,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos].0
+    }
+
+    fn next(&mut self) -> Token {
+        let t = self.tokens[self.pos].0.clone();
+        if t != Token::End {
+            self.pos += 1;
+        }
+        t
+    }
+
+    fn error(&self, msg: &str) -> ParseError {
+        self.error_at(self.pos, msg)
+    }
+
+    fn error_at(&self, pos: usize, msg: &str) -> ParseError {
+        let (_, line, col) = self.tokens[pos];
+        ParseError::new(line, &format!("column {}: {}", col, msg))
+    }
+
+    fn expect(&mut self, t: Token, what: &str) -> Result<(), ParseError> {
+        if *self.peek() == t {
+            self.next();
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected {}", what)))
+        }
+    }
+
+    /// the variable named `name`, which is the token just consumed
+    fn var(&mut self, name: &str) -> Result<usize, ParseError> {
+        match (self.lookup)(name) {
+            Some(v) => Ok(v),
+            None => Err(self.error_at(self.pos - 1, &format!("unknown variable `{}`", name))),
+        }
+    }
+
+    /// parses a left-associative chain of operands of `op`, combining them
+    /// with `combine`
+    fn chain<P, C>(&mut self, op: Token, operand: P, combine: C) -> Result<BoolExpr, ParseError>
+    where
+        P: Fn(&mut Parser<'a>) -> Result<BoolExpr, ParseError>,
+        C: Fn(Vec<BoolExpr>) -> BoolExpr,
+    {
+        let mut v = vec![operand(self)?];
+        while *self.peek() == op {
+            self.next();
+            v.push(operand(self)?);
+        }
+        Ok(if v.len() == 1 { v.pop().unwrap() } else { combine(v) })
+    }
+
+    fn iff(&mut self) -> Result<BoolExpr, ParseError> {
+        self.chain(Token::Iff, Parser::implies, |v| {
+            let mut it = v.into_iter();
+            let first = it.next().unwrap();
+            it.fold(first, |acc, e| BoolExpr::Iff(Box::new(acc), Box::new(e)))
+        })
+    }
+
+    /// implication is right-associative
+    fn implies(&mut self) -> Result<BoolExpr, ParseError> {
+        let l = self.or()?;
+        if *self.peek() == Token::Implies {
+            self.next();
+            let r = self.implies()?;
+            Ok(BoolExpr::Implies(Box::new(l), Box::new(r)))
+        } else {
+            Ok(l)
+        }
+    }
+
+    fn or(&mut self) -> Result<BoolExpr, ParseError> {
+        self.chain(Token::Or, Parser::xor, |mut v| {
+            if v.len() == 2 {
+                let r = v.pop().unwrap();
+                let l = v.pop().unwrap();
+                BoolExpr::Or(Box::new(l), Box::new(r))
+            } else {
+                BoolExpr::Disj(v)
+            }
+        })
+    }
+
+    fn xor(&mut self) -> Result<BoolExpr, ParseError> {
+        self.chain(Token::Xor, Parser::and, |v| {
+            let mut it = v.into_iter();
+            let first = it.next().unwrap();
+            it.fold(first, |acc, e| BoolExpr::Xor(Box::new(acc), Box::new(e)))
+        })
+    }
+
+    fn and(&mut self) -> Result<BoolExpr, ParseError> {
+        self.chain(Token::And, Parser::unary, |mut v| {
+            if v.len() == 2 {
+                let r = v.pop().unwrap();
+                let l = v.pop().unwrap();
+                BoolExpr::And(Box::new(l), Box::new(r))
+            } else {
+                BoolExpr::Conj(v)
+            }
+        })
+    }
+
+    /// a negated variable is parsed as a negative literal
+    fn unary(&mut self) -> Result<BoolExpr, ParseError> {
+        if *self.peek() != Token::Not {
+            return self.atom();
+        }
+        self.next();
+        match self.peek().clone() {
+            Token::Ident(ref name)
+                if name != "true"
+                    && name != "false"
+                    && name != "ite"
+                    && name != "and"
+                    && name != "or" =>
+            {
+                self.next();
+                Ok(BoolExpr::Var(self.var(name)?, false))
+            }
+            _ => Ok(BoolExpr::Not(Box::new(self.unary()?))),
+        }
+    }
+
+    /// a parenthesized, comma-separated and possibly empty argument list
+    fn args(&mut self) -> Result<Vec<BoolExpr>, ParseError> {
+        self.expect(Token::LParen, "`(`")?;
+        let mut v = Vec::new();
+        if *self.peek() == Token::RParen {
+            self.next();
+            return Ok(v);
+        }
+        loop {
+            v.push(self.iff()?);
+            if *self.peek() != Token::Comma {
+                break;
+            }
+            self.next();
+        }
+        self.expect(Token::RParen, "`)`")?;
+        Ok(v)
+    }
+
+    fn atom(&mut self) -> Result<BoolExpr, ParseError> {
+        match self.peek().clone() {
+            Token::LParen => {
+                self.next();
+                let e = self.iff()?;
+                self.expect(Token::RParen, "`)`")?;
+                Ok(e)
+            }
+            Token::Ident(name) => {
+                self.next();
+                match name.as_str() {
+                    "true" => Ok(BoolExpr::Const(true)),
+                    "false" => Ok(BoolExpr::Const(false)),
+                    "ite" => {
+                        self.expect(Token::LParen, "`(`")?;
+                        let c = self.iff()?;
+                        self.expect(Token::Comma, "`,`")?;
+                        let t = self.iff()?;
+                        self.expect(Token::Comma, "`,`")?;
+                        let e = self.iff()?;
+                        self.expect(Token::RParen, "`)`")?;
+                        Ok(BoolExpr::Ite(Box::new(c), Box::new(t), Box::new(e)))
+                    }
+                    "and" => Ok(BoolExpr::Conj(self.args()?)),
+                    "or" => Ok(BoolExpr::Disj(self.args()?)),
+                    _ => Ok(BoolExpr::Var(self.var(&name)?, true)),
+                }
+            }
+            _ => Err(self.error("expected an expression")),
+        }
+    }
+}
+
+impl BoolExpr {
+    /// Parses an infix Boolean expression. Operators, from loosest to tightest
+    /// binding, are `<->` (or `<=>`), `->` (or `=>`, right-associative), `|`
+    /// (or `||`), `^`, `&` (or `&&`), and `!` (or `~`); `true`, `false`, and
+    /// `ite(c, t, e)` are also supported. Chains of three or more `&` or `|`
+    /// operands are parsed as n-ary nodes, as are `and(...)` and `or(...)`
+    /// with any number of arguments.
+    ///
+    /// Variables are named by identifiers; `names` maps names to variable
+    /// indices, and names which are not in it are added with fresh indices.
+    pub fn parse(s: &str, names: &mut HashMap<String, usize>) -> Result<BoolExpr, ParseError> {
+        BoolExpr::parse_with(s, &mut |name| {
+            let next = names.values().map(|v| v + 1).max().unwrap_or(0);
+            Some(*names.entry(String::from(name)).or_insert(next))
+        })
+    }
+
+    /// Parses like `parse`, but resolves each variable name with `lookup`;
+    /// names for which it returns None are errors
+    pub fn parse_with(
+        s: &str,
+        lookup: &mut dyn FnMut(&str) -> Option<usize>,
+    ) -> Result<BoolExpr, ParseError> {
+        let mut p = Parser {
+            tokens: tokenize(s)?,
+            pos: 0,
+            lookup: lookup,
+        };
+        let e = p.iff()?;
+        if *p.peek() != Token::End {
+            return Err(p.error("expected an operator"));
+        }
+        Ok(e)
+    }
+}
+
+/// Prints the expression in the syntax accepted by `BoolExpr::parse`, naming
+/// variable `i` as `xi`; n-ary nodes with fewer than three operands print as
+/// `and(...)` or `or(...)` so that they parse back unchanged
+impl fmt::Display for BoolExpr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fn join(f: &mut fmt::Formatter, v: &[BoolExpr], op: &str, call: &str) -> fmt::Result {
+            if v.len() < 3 {
+                write!(f, "{}(", call)?;
+            } else {
+                write!(f, "(")?;
+            }
+            for (i, e) in v.iter().enumerate() {
+                if i > 0 && v.len() < 3 {
+                    write!(f, ", ")?;
+                } else if i > 0 {
+                    write!(f, " {} ", op)?;
+                }
+                write!(f, "{}", e)?;
+            }
+            write!(f, ")")
+        }
+        match self {
+            &BoolExpr::Var(lbl, true) => write!(f, "x{}", lbl),
+            &BoolExpr::Var(lbl, false) => write!(f, "!x{}", lbl),
+            &BoolExpr::Const(v) => write!(f, "{}", v),
+            &BoolExpr::Not(ref e) => write!(f, "!({})", e),
+            &BoolExpr::And(ref l, ref r) => write!(f, "({} & {})", l, r),
+            &BoolExpr::Or(ref l, ref r) => write!(f, "({} | {})", l, r),
+            &BoolExpr::Xor(ref l, ref r) => write!(f, "({} ^ {})", l, r),
+            &BoolExpr::Iff(ref l, ref r) => write!(f, "({} <-> {})", l, r),
+            &BoolExpr::Implies(ref l, ref r) => write!(f, "({} -> {})", l, r),
+            &BoolExpr::Ite(ref c, ref t, ref e) => write!(f, "ite({}, {}, {})", c, t, e),
+            &BoolExpr::Conj(ref v) => join(f, v, "&", "and"),
+            &BoolExpr::Disj(ref v) => join(f, v, "|", "or"),
+        }
+    }
+}
+
+/// Generates expressions of every kind over 6 variables
+impl Arbitrary for BoolExpr {
+    fn arbitrary(g: &mut Gen) -> BoolExpr {
+        fn helper(g: &mut Gen, depth: usize) -> BoolExpr {
+            let b = |g: &mut Gen| Box::new(helper(g, depth - 1));
+            let kind = if depth == 0 { 0 } else { u8::arbitrary(g) % 11 };
+            match kind {
+                0 | 1 => BoolExpr::Var(usize::arbitrary(g) % 6, bool::arbitrary(g)),
+                2 => BoolExpr::Not(b(g)),
+                3 => BoolExpr::And(b(g), b(g)),
+                4 => BoolExpr::Or(b(g), b(g)),
+                5 => BoolExpr::Xor(b(g), b(g)),
+                6 => BoolExpr::Iff(b(g), b(g)),
+                7 => BoolExpr::Implies(b(g), b(g)),
+                8 => BoolExpr::Ite(b(g), b(g), b(g)),
+                9 | 10 => {
+                    let len = usize::arbitrary(g) % 6;
+                    let v = (0..len).map(|_| helper(g, depth - 1)).collect();
+                    if kind == 9 {
+                        BoolExpr::Conj(v)
+                    } else {
+                        BoolExpr::Disj(v)
+                    }
+                }
+                _ => unreachable!(),
+            }
+        }
+        if u8::arbitrary(g) % 16 == 0 {
+            return BoolExpr::Const(bool::arbitrary(g));
+        }
+        helper(g, 4)
+    }
+}
+
+#[cfg(test)]
+mod test_boolexpr {
+    use super::*;
+    use manager::rsbdd_manager::{BddManager, BddWmc};
+    use manager::sdd_manager::{even_split, SddManager};
+    use quickcheck::TestResult;
+
+    #[test]
+    fn test_parse_precedence() {
+        let mut names = HashMap::new();
+        let e = BoolExpr::parse("a | !b & c -> d -> e <-> f ^ g", &mut names).unwrap();
+        let v = |i| Box::new(BoolExpr::Var(i, true));
+        let expected = BoolExpr::Iff(
+            Box::new(BoolExpr::Implies(
+                Box::new(BoolExpr::Or(
+                    v(0),
+                    Box::new(BoolExpr::And(Box::new(BoolExpr::Var(1, false)), v(2))),
+                )),
+                Box::new(BoolExpr::Implies(v(3), v(4))),
+            )),
+            Box::new(BoolExpr::Xor(v(5), v(6))),
+        );
+        assert_eq!(e, expected);
+        assert_eq!(names["g"], 6);
+
+        // known names keep their indices, and chains become n-ary nodes
+        let e = BoolExpr::parse("!!c && (b || a || true)", &mut names).unwrap();
+        let expected = BoolExpr::And(
+            Box::new(BoolExpr::Not(Box::new(BoolExpr::Var(2, false)))),
+            Box::new(BoolExpr::Disj(vec![
+                BoolExpr::Var(1, true),
+                BoolExpr::Var(0, true),
+                BoolExpr::Const(true),
+            ])),
+        );
+        assert_eq!(e, expected);
+        assert_eq!(names.len(), 7);
+
+        // short n-ary nodes are written as calls
+        let e = BoolExpr::parse("and() | or(a) | and(b, !c)", &mut names).unwrap();
+        let expected = BoolExpr::Disj(vec![
+            BoolExpr::Conj(vec![]),
+            BoolExpr::Disj(vec![BoolExpr::Var(0, true)]),
+            BoolExpr::Conj(vec![BoolExpr::Var(1, true), BoolExpr::Var(2, false)]),
+        ]);
+        assert_eq!(e, expected);
+        assert_eq!(e.to_string(), "(and() | or(x0) | and(x1, !x2))");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let mut names = HashMap::new();
+        let err = BoolExpr::parse("a &\n(b | )", &mut names).unwrap_err();
+        assert_eq!(err.line, 2);
+        assert_eq!(err.msg, "column 6: expected an expression");
+        let msg = |s| BoolExpr::parse(s, &mut HashMap::new()).unwrap_err().msg;
+        assert_eq!(msg("a $ b"), "column 3: unexpected character `$`");
+        assert_eq!(msg("ite(a, b)"), "column 9: expected `,`");
+        assert_eq!(msg("a b"), "column 3: expected an operator");
+        assert_eq!(msg("and(a b)"), "column 7: expected `)`");
+    }
+
+    quickcheck! {
+        fn to_cnf_eq_compile(e: BoolExpr) -> TestResult {
+            let cnf = match e.to_cnf(500) {
+                Some(c) => c,
+                None => return TestResult::discard(),
+            };
+            let mut bdd_man = BddManager::new_default_order(6);
+            let expected = bdd_man.from_boolexpr(&e);
+            let bdd = bdd_man.from_cnf(&cnf);
+
+            let order: Vec<VarLabel> = (0..6).map(|x| VarLabel::new(x)).collect();
+            let mut sdd_man = SddManager::new(even_split(&order, 2));
+            let expected_sdd = sdd_man.from_boolexpr(&e);
+            let sdd = sdd_man.from_cnf(&cnf);
+            TestResult::from_bool(bdd == expected && sdd == expected_sdd)
+        }
+    }
+
+    quickcheck! {
+        fn tseitin_preserves_count(e: BoolExpr) -> bool {
+            let (cnf, aux) = e.tseitin(6);
+            let weights = |n: u64| BddWmc::new_with_default(
+                0, 1, (0..n).map(|v| (VarLabel::new(v), (1, 1))).collect());
+
+            let mut small = BddManager::new_default_order(6);
+            let f = small.from_boolexpr(&e);
+            let expected = small.wmc(f, &weights(6));
+
+            // the auxiliary variables are defined by the original ones, so
+            // forgetting them recovers the expression
+            let num_vars = 6 + aux.len();
+            let mut man = BddManager::new_default_order(num_vars);
+            let g = man.from_cnf(&cnf);
+            let count = man.wmc(g, &weights(num_vars as u64));
+            let projected = aux.iter().fold(g, |acc, v| man.exists(acc, *v));
+            let f = man.from_boolexpr(&e);
+            count == expected && projected == f
+        }
+    }
+
+    quickcheck! {
+        fn print_parse_roundtrip(e: BoolExpr) -> bool {
+            let mut names: HashMap<String, usize> =
+                (0..6).map(|i| (format!("x{}", i), i)).collect();
+            BoolExpr::parse(&e.to_string(), &mut names) == Ok(e)
+        }
+    }
+}
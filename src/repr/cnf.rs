@@ -1,10 +1,12 @@
+use manager::rsbdd_manager::BddWmc;
 use manager::var_order::VarOrder;
 use rand;
 use rand::distributions::IndependentSample;
 use rand::StdRng;
 use rand::{thread_rng, Rng};
 use std::collections::HashMap;
-use repr::var_label::{Literal, VarLabel};
+use repr::var_label::{Literal, VarLabel, MAX_VAR_SIZE};
+use serialize::ParseError;
 use std::cmp::{max, min};
 extern crate quickcheck;
 use self::quickcheck::{Arbitrary, Gen};
@@ -56,35 +58,146 @@ impl Iterator for AssignmentIter {
 
 
 impl Cnf {
+    /// Reads a DIMACS CNF file; panics if the file is malformed. Use
+    /// `Cnf::from_dimacs` to handle errors or read weights.
     pub fn from_file(v: String) -> Cnf {
-        use dimacs::*;
-        let r = parse_dimacs(&v).unwrap();
-        let (_, cvec) = match r {
-            Instance::Cnf { num_vars, clauses } => (num_vars, clauses),
-            _ => panic!(),
-        };
-        let mut clause_vec: Vec<Vec<Literal>> = Vec::new();
-        let mut m = 0;
-        for itm in cvec.iter() {
-            let mut lit_vec: Vec<Literal> = Vec::new();
-            for l in itm.lits().iter() {
-                let b = match l.sign() {
-                    Sign::Neg => false,
-                    Sign::Pos => true,
-                };
-                // subtract 1, we are 0-indexed
-                let lbl = VarLabel::new(l.var().to_u64() - 1);
-                m = max(l.var().to_u64() as usize, m);
-                lit_vec.push(Literal::new(lbl, b));
+        match Cnf::from_dimacs(&v) {
+            Ok(w) => w.cnf,
+            Err(e) => panic!("invalid DIMACS file: {}", e),
+        }
+    }
+
+    /// Reads a DIMACS CNF file, along with any model-counting-competition
+    /// `c p weight <lit> <weight> 0` and `c p show <vars> 0` lines. Clauses
+    /// may span several lines; a `%` line ends the file.
+    pub fn from_dimacs(s: &str) -> Result<WeightedCnf, ParseError> {
+        fn num<T: ::std::str::FromStr>(line: usize, tok: &str) -> Result<T, ParseError> {
+            tok.parse()
+                .map_err(|_| ParseError::new(line, &format!("invalid number `{}`", tok)))
+        }
+
+        // the number of variables in the `p cnf` line
+        let mut header: Option<usize> = None;
+        let mut clauses: Vec<Vec<Literal>> = Vec::new();
+        let mut clause: Vec<Literal> = Vec::new();
+        let mut weights: HashMap<VarLabel, (f64, f64)> = HashMap::new();
+        let mut show: Option<Vec<VarLabel>> = None;
+        let mut last_line = 1;
+        for (i, l) in s.lines().enumerate() {
+            let line = i + 1;
+            last_line = line;
+            let toks: Vec<&str> = l.split_whitespace().collect();
+            if toks.is_empty() {
+                continue;
+            }
+            if toks[0] == "%" {
+                break;
+            }
+            // the variable of a 1-based DIMACS literal; variables beyond the
+            // count in the header are accepted, since many benchmark files
+            // have them
+            let var = |lit: i64| -> Result<VarLabel, ParseError> {
+                if header.is_none() {
+                    Err(ParseError::new(line, "missing `p cnf` header"))
+                } else if lit == 0 || lit.unsigned_abs() > MAX_VAR_SIZE as u64 {
+                    Err(ParseError::new(line, &format!("invalid variable {}", lit)))
+                } else {
+                    Ok(VarLabel::new(lit.unsigned_abs() - 1))
+                }
+            };
+            if toks[0] == "c" {
+                if toks.get(1) != Some(&"p") {
+                    continue;
+                }
+                match toks.get(2) {
+                    Some(&"weight") => {
+                        if toks.len() != 6 || toks[5] != "0" {
+                            let msg = "expected `c p weight <lit> <weight> 0`";
+                            return Err(ParseError::new(line, msg));
+                        }
+                        let lit: i64 = num(line, toks[3])?;
+                        let w: f64 = num(line, toks[4])?;
+                        let entry = weights.entry(var(lit)?).or_insert((1.0, 1.0));
+                        if lit > 0 {
+                            entry.1 = w;
+                        } else {
+                            entry.0 = w;
+                        }
+                    }
+                    Some(&"show") => {
+                        if toks.last() != Some(&"0") {
+                            return Err(ParseError::new(line, "`c p show` must end with 0"));
+                        }
+                        let shown = show.get_or_insert(Vec::new());
+                        for t in toks[3..toks.len() - 1].iter() {
+                            let v: i64 = num(line, t)?;
+                            if v < 0 {
+                                let msg = "shown variables must be positive";
+                                return Err(ParseError::new(line, msg));
+                            }
+                            shown.push(var(v)?);
+                        }
+                    }
+                    // other `c p` lines (such as `c p auto`) are ignored
+                    _ => (),
+                }
+                continue;
+            }
+            if toks[0] == "p" {
+                if header.is_some() {
+                    return Err(ParseError::new(line, "duplicate `p` line"));
+                }
+                if toks.len() != 4 || toks[1] != "cnf" {
+                    return Err(ParseError::new(line, "expected `p cnf <vars> <clauses>`"));
+                }
+                // the clause count is not checked, since many benchmark files
+                // get it wrong
+                num::<usize>(line, toks[3])?;
+                header = Some(num(line, toks[2])?);
+                continue;
+            }
+            for t in toks.iter() {
+                let lit: i64 = num(line, t)?;
+                if lit == 0 {
+                    if clause.is_empty() {
+                        return Err(ParseError::new(line, "empty clauses are not supported"));
+                    }
+                    clauses.push(clause);
+                    clause = Vec::new();
+                } else {
+                    clause.push(Literal::new(var(lit)?, lit > 0));
+                }
             }
-            clause_vec.push(lit_vec);
         }
-        Cnf {
-            clauses: clause_vec,
-            num_vars: m + 1,
+        // the final clause may omit its terminating 0
+        if !clause.is_empty() {
+            clauses.push(clause);
         }
+        let num_vars = match header {
+            Some(n) => n,
+            None => return Err(ParseError::new(last_line, "missing `p cnf` header")),
+        };
+        let mut cnf = Cnf::new(clauses);
+        cnf.num_vars = max(cnf.num_vars, num_vars);
+        Ok(WeightedCnf {
+            cnf: cnf,
+            weights: weights,
+            show: show,
+        })
     }
 
+    /// Writes the CNF in the DIMACS format, numbering variables from 1
+    pub fn to_dimacs(&self) -> String {
+        let mut r = format!("p cnf {} {}\n", self.num_vars, self.clauses.len());
+        for clause in self.clauses.iter() {
+            for lit in clause.iter() {
+                let v = lit.get_label().value() as i64 + 1;
+                r.push_str(&format!("{} ", if lit.get_polarity() { v } else { -v }));
+            }
+            r.push_str("0\n");
+        }
+        r
+    }
 
     pub fn rand_cnf(rng: &mut StdRng, num_vars: usize, num_clauses: usize) -> Cnf {
         assert!(num_clauses > 2, "requires at least 2 clauses in CNF");
@@ -291,6 +404,54 @@ impl Cnf {
 }
 
 
+/// A CNF with literal weights and projection variables, as used in the model
+/// counting competition
+#[derive(Debug, Clone)]
+pub struct WeightedCnf {
+    pub cnf: Cnf,
+    /// the `(low, high)` weights of the variables which have weight lines;
+    /// literals without a weight line have weight 1
+    pub weights: HashMap<VarLabel, (f64, f64)>,
+    /// the variables listed on `c p show` lines, if there are any
+    pub show: Option<Vec<VarLabel>>,
+}
+
+impl WeightedCnf {
+    /// The weights of every variable of the CNF, for use with a `BddManager`
+    pub fn bdd_weights(&self) -> BddWmc<f64> {
+        let weights = (0..self.cnf.num_vars())
+            .map(|v| {
+                let lbl = VarLabel::new(v as u64);
+                (lbl, *self.weights.get(&lbl).unwrap_or(&(1.0, 1.0)))
+            })
+            .collect();
+        BddWmc::new_with_default(0.0, 1.0, weights)
+    }
+
+    /// Writes the CNF in the DIMACS format, followed by its weight and show
+    /// lines
+    pub fn to_dimacs(&self) -> String {
+        let mut r = self.cnf.to_dimacs();
+        let mut weighted: Vec<(&VarLabel, &(f64, f64))> = self.weights.iter().collect();
+        weighted.sort_by_key(|&(v, _)| v.value());
+        for (v, &(low, high)) in weighted.into_iter() {
+            let v = v.value() + 1;
+            r.push_str(&format!("c p weight {} {} 0\nc p weight -{} {} 0\n", v, high, v, low));
+        }
+        match self.show {
+            Some(ref show) => {
+                r.push_str("c p show");
+                for v in show.iter() {
+                    r.push_str(&format!(" {}", v.value() + 1));
+                }
+                r.push_str(" 0\n");
+            }
+            None => (),
+        }
+        r
+    }
+}
+
 impl Arbitrary for Cnf {
     fn arbitrary(g: &mut Gen) -> Cnf {
         let num_vars = (u64::arbitrary(g) % 8) + 1;
@@ -316,4 +477,53 @@ fn test_cnf_wmc() {
         VarLabel::new(1) => (1, 1),
     };
     assert_eq!(cnf.wmc(&weights), 3);
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_dimacs_roundtrip() {
+    let files = [
+        include_str!("../../cnf/count.cnf"),
+        include_str!("../../cnf/unsat-1.cnf"),
+        include_str!("../../cnf/bench-01.cnf"),
+    ];
+    for f in files.iter() {
+        let cnf = Cnf::from_dimacs(f).unwrap().cnf;
+        let read = Cnf::from_dimacs(&cnf.to_dimacs()).unwrap().cnf;
+        assert_eq!(cnf, read);
+    }
+}
+
+#[test]
+fn test_dimacs_weights() {
+    use manager::rsbdd_manager::BddManager;
+    // (x1 || !x2) && x3 spread over two lines, with x3 unweighted
+    let s = "c an example\np cnf 3 2\nc p weight 1 0.25 0\nc p weight -1 0.75 0\n\
+             c p weight -2 0.5 0\n1 -2\n0 3 0\nc p show 1 2 0\n";
+    let w = Cnf::from_dimacs(s).unwrap();
+    assert_eq!(w.cnf.clauses().len(), 2);
+    assert_eq!(w.weights[&VarLabel::new(1)], (0.5, 1.0));
+    assert_eq!(w.show, Some(vec![VarLabel::new(0), VarLabel::new(1)]));
+
+    let mut man = BddManager::new_default_order(3);
+    let f = man.from_cnf(&w.cnf);
+    // x1 = T, x2 = T; x1 = T, x2 = F; x1 = F, x2 = F
+    let expected = 0.25 * 1.0 + 0.25 * 0.5 + 0.75 * 0.5;
+    assert!((man.wmc(f, &w.bdd_weights()) - expected).abs() < 1e-9);
+
+    let read = Cnf::from_dimacs(&w.to_dimacs()).unwrap();
+    assert_eq!(read.cnf, w.cnf);
+    assert_eq!(read.weights, w.weights);
+    assert_eq!(read.show, w.show);
+}
+
+#[test]
+fn test_dimacs_errors() {
+    let line = |s: &str| Cnf::from_dimacs(s).unwrap_err().line;
+    assert_eq!(line("1 2 0\n"), 1);
+    assert_eq!(line("p cnf 2 1\nc p show 0 0\n1 0\n"), 2);
+    assert_eq!(line("p cnf 2 1\n1 x 0\n"), 2);
+    assert_eq!(line("p cnf 2 1\nc p weight 1 0.5\n1 0\n"), 2);
+    assert_eq!(line("p cnf 2 1\n1 0\n0\n"), 3);
+    assert_eq!(line("p dnf 2 1\n"), 1);
+    assert_eq!(line("p cnf 2 1\n-9223372036854775808 0\n"), 2);
+}
@@ -1,5 +1,7 @@
 pub mod bdd;
 pub mod boolexpr;
 pub mod cnf;
+pub mod nnf;
+pub mod pb;
 pub mod sdd;
 pub mod var_label;
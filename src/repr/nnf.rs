@@ -0,0 +1,358 @@
+//! Circuits in negation normal form (NNF), read from and written to the c2d
+//! `.nnf` text format.
+//!
+//! A c2d file starts with the header `nnf <nodes> <edges> <vars>` and lists
+//! one node per line, children before parents; nodes are referred to by their
+//! (0-based) position in the list and the root is the last node:
+//!   - `L <lit>` is a literal, with variables numbered from 1
+//!   - `A <n> <children>` is a conjunction; `A 0` is true
+//!   - `O <var> <n> <children>` is a disjunction, where `var` is nonzero if
+//!     the children are exclusive because they disagree on that variable; `O 0
+//!     0` is false
+
+use num::traits::Num;
+use repr::var_label::{VarLabel, VarNames, MAX_VAR_SIZE};
+use serialize::{self, ParseError};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum NnfNode {
+    Lit(VarLabel, bool),
+    And(Vec<usize>),
+    /// a disjunction, with the variable on which its children are decided (if
+    /// any)
+    Or(Option<VarLabel>, Vec<usize>),
+}
+
+/// An NNF circuit. Nodes are hash-consed and stored children before parents;
+/// the root is the last node.
+#[derive(Debug, Clone)]
+pub struct Nnf {
+    nodes: Vec<NnfNode>,
+    num_vars: usize,
+    table: HashMap<NnfNode, usize>,
+    names: VarNames,
+}
+
+impl Nnf {
+    /// An empty circuit over `num_vars` variables
+    pub fn new(num_vars: usize) -> Nnf {
+        Nnf {
+            nodes: Vec::new(),
+            num_vars: num_vars,
+            table: HashMap::new(),
+            names: VarNames::new(),
+        }
+    }
+
+    /// Adds `node` to the circuit, returning its index; structurally equal
+    /// nodes are shared. The children of `node` must already be in the circuit.
+    pub fn add(&mut self, node: NnfNode) -> usize {
+        match self.table.get(&node) {
+            Some(idx) => return *idx,
+            None => (),
+        }
+        self.nodes.push(node.clone());
+        self.table.insert(node, self.nodes.len() - 1);
+        self.nodes.len() - 1
+    }
+
+    pub fn true_node(&mut self) -> usize {
+        self.add(NnfNode::And(Vec::new()))
+    }
+
+    pub fn false_node(&mut self) -> usize {
+        self.add(NnfNode::Or(None, Vec::new()))
+    }
+
+    pub fn nodes(&self) -> &[NnfNode] {
+        &self.nodes
+    }
+
+    pub fn num_vars(&self) -> usize {
+        self.num_vars
+    }
+
+    /// The names of the circuit's variables, which are stored in c2d files as
+    /// `c name <var> <name>` comments
+    pub fn names(&self) -> &VarNames {
+        &self.names
+    }
+
+    pub fn names_mut(&mut self) -> &mut VarNames {
+        &mut self.names
+    }
+
+    pub fn num_edges(&self) -> usize {
+        self.nodes
+            .iter()
+            .map(|n| match n {
+                &NnfNode::Lit(_, _) => 0,
+                &NnfNode::And(ref c) => c.len(),
+                &NnfNode::Or(_, ref c) => c.len(),
+            })
+            .sum()
+    }
+
+    /// Makes the node at index `root` the root of the circuit by moving it to
+    /// the end, dropping every node which is not below it
+    pub fn set_root(&mut self, root: usize) {
+        let mut keep = vec![false; self.nodes.len()];
+        keep[root] = true;
+        for i in (0..root + 1).rev() {
+            if !keep[i] {
+                continue;
+            }
+            match &self.nodes[i] {
+                &NnfNode::And(ref c) | &NnfNode::Or(_, ref c) => {
+                    for j in c.iter() {
+                        keep[*j] = true;
+                    }
+                }
+                _ => (),
+            }
+        }
+        let mut new_idx = vec![0; self.nodes.len()];
+        let mut r = Nnf::new(self.num_vars);
+        for i in 0..root + 1 {
+            if !keep[i] {
+                continue;
+            }
+            let node = match &self.nodes[i] {
+                &NnfNode::Lit(v, p) => NnfNode::Lit(v, p),
+                &NnfNode::And(ref c) => NnfNode::And(c.iter().map(|j| new_idx[*j]).collect()),
+                &NnfNode::Or(d, ref c) => NnfNode::Or(d, c.iter().map(|j| new_idx[*j]).collect()),
+            };
+            new_idx[i] = r.add(node);
+        }
+        r.names = self.names.clone();
+        *self = r;
+    }
+
+    /// Folds the circuit bottom-up, returning the value of the root; panics
+    /// if the circuit is empty
+    fn fold<T: Clone, F>(&self, f: F) -> T
+    where
+        F: Fn(&NnfNode, &[T]) -> T,
+    {
+        let mut vals: Vec<T> = Vec::with_capacity(self.nodes.len());
+        for n in self.nodes.iter() {
+            let v = f(n, &vals);
+            vals.push(v);
+        }
+        vals.pop().expect("empty NNF")
+    }
+
+    /// Evaluates the circuit on a total assignment to its variables; panics
+    /// if a variable of the circuit is missing from `assgn`
+    pub fn eval(&self, assgn: &HashMap<VarLabel, bool>) -> bool {
+        self.fold(|n, vals: &[bool]| match n {
+            &NnfNode::Lit(v, p) => match assgn.get(&v) {
+                Some(b) => *b == p,
+                None => panic!("variable {} is missing from the assignment", v.value()),
+            },
+            &NnfNode::And(ref c) => c.iter().all(|i| vals[*i]),
+            &NnfNode::Or(_, ref c) => c.iter().any(|i| vals[*i]),
+        })
+    }
+
+    /// Decides satisfiability; only correct if the circuit is decomposable
+    pub fn is_sat(&self) -> bool {
+        self.fold(|n, vals: &[bool]| match n {
+            &NnfNode::Lit(_, _) => true,
+            &NnfNode::And(ref c) => c.iter().all(|i| vals[*i]),
+            &NnfNode::Or(_, ref c) => c.iter().any(|i| vals[*i]),
+        })
+    }
+
+    /// Computes the weighted model count, where `weights` maps each variable
+    /// to its `(low, high)` weight; only correct if the circuit is smooth,
+    /// deterministic and decomposable. Panics if a variable of the circuit is
+    /// missing from `weights`
+    pub fn wmc<T: Num + Copy>(&self, weights: &HashMap<VarLabel, (T, T)>) -> T {
+        self.fold(|n, vals: &[T]| match n {
+            &NnfNode::Lit(v, p) => {
+                let (low, high) = match weights.get(&v) {
+                    Some(w) => *w,
+                    None => panic!("variable {} is missing from the weights", v.value()),
+                };
+                if p {
+                    high
+                } else {
+                    low
+                }
+            }
+            &NnfNode::And(ref c) => c.iter().fold(T::one(), |acc, i| acc * vals[*i]),
+            &NnfNode::Or(_, ref c) => c.iter().fold(T::zero(), |acc, i| acc + vals[*i]),
+        })
+    }
+
+    /// Writes the circuit in the c2d format
+    pub fn to_c2d(&self) -> String {
+        let mut r = serialize::write_names(&self.names);
+        r.push_str(&format!(
+            "nnf {} {} {}\n",
+            self.nodes.len(),
+            self.num_edges(),
+            self.num_vars
+        ));
+        for n in self.nodes.iter() {
+            let line = match n {
+                &NnfNode::Lit(v, p) => {
+                    let var = v.value() as i64 + 1;
+                    format!("L {}", if p { var } else { -var })
+                }
+                &NnfNode::And(ref c) => {
+                    let c: Vec<String> = c.iter().map(|i| i.to_string()).collect();
+                    format!("A {}{}{}", c.len(), if c.is_empty() { "" } else { " " }, c.join(" "))
+                }
+                &NnfNode::Or(d, ref c) => {
+                    let d = d.map(|v| v.value() + 1).unwrap_or(0);
+                    let c: Vec<String> = c.iter().map(|i| i.to_string()).collect();
+                    format!(
+                        "O {} {}{}{}",
+                        d,
+                        c.len(),
+                        if c.is_empty() { "" } else { " " },
+                        c.join(" ")
+                    )
+                }
+            };
+            r.push_str(&line);
+            r.push('\n');
+        }
+        r
+    }
+
+    /// Reads a circuit in the c2d format; comment lines start with `c`, and
+    /// those written for the circuit's names are read back into them
+    pub fn from_c2d(s: &str) -> Result<Nnf, ParseError> {
+        fn num<T: ::std::str::FromStr>(line: usize, tok: Option<&&str>) -> Result<T, ParseError> {
+            match tok {
+                Some(t) => t
+                    .parse()
+                    .map_err(|_| ParseError::new(line, &format!("invalid number `{}`", t))),
+                None => Err(ParseError::new(line, "missing field")),
+            }
+        }
+        fn var(line: usize, v: i64, num_vars: usize) -> Result<VarLabel, ParseError> {
+            if v == 0 || v.unsigned_abs() > MAX_VAR_SIZE.min(num_vars) as u64 {
+                return Err(ParseError::new(line, "variable out of range"));
+            }
+            Ok(VarLabel::new(v.unsigned_abs() - 1))
+        }
+
+        let mut lines = s
+            .lines()
+            .enumerate()
+            .map(|(i, l)| (i + 1, l.split_whitespace().collect::<Vec<&str>>()))
+            .filter(|&(_, ref toks)| toks.len() > 0 && toks[0] != "c");
+        let (hline, header) = match lines.next() {
+            Some(h) => h,
+            None => return Err(ParseError::new(1, "empty file")),
+        };
+        if header[0] != "nnf" {
+            return Err(ParseError::new(hline, "expected `nnf` header"));
+        }
+        let num_nodes: usize = num(hline, header.get(1))?;
+        let num_vars: usize = num(hline, header.get(3))?;
+
+        // nodes are not hash-consed while reading so that their indices match
+        // the file
+        let mut nodes = Vec::new();
+        for (line, toks) in lines {
+            let children = |start: usize| -> Result<Vec<usize>, ParseError> {
+                let n: usize = num(line, toks.get(start))?;
+                let mut c = Vec::new();
+                for i in 0..n {
+                    let idx: usize = num(line, toks.get(start + 1 + i))?;
+                    if idx >= nodes.len() {
+                        return Err(ParseError::new(line, "child is not defined before its parent"));
+                    }
+                    c.push(idx);
+                }
+                Ok(c)
+            };
+            let node = match toks[0] {
+                "L" => {
+                    let lit: i64 = num(line, toks.get(1))?;
+                    NnfNode::Lit(var(line, lit, num_vars)?, lit > 0)
+                }
+                "A" => NnfNode::And(children(1)?),
+                "O" => {
+                    let d: i64 = num(line, toks.get(1))?;
+                    let d = if d == 0 { None } else { Some(var(line, d, num_vars)?) };
+                    NnfNode::Or(d, children(2)?)
+                }
+                t => return Err(ParseError::new(line, &format!("unknown node type `{}`", t))),
+            };
+            nodes.push(node);
+        }
+        if nodes.len() != num_nodes {
+            return Err(ParseError::new(hline, "node count does not match the header"));
+        }
+        if nodes.is_empty() {
+            return Err(ParseError::new(hline, "circuit has no nodes"));
+        }
+        let mut r = Nnf::new(num_vars);
+        let mut new_idx = Vec::new();
+        for n in nodes.into_iter() {
+            let n = match n {
+                NnfNode::And(c) => NnfNode::And(c.iter().map(|i| new_idx[*i]).collect()),
+                NnfNode::Or(d, c) => NnfNode::Or(d, c.iter().map(|i| new_idx[*i]).collect()),
+                lit => lit,
+            };
+            new_idx.push(r.add(n));
+        }
+        for (v, name) in serialize::read_names(s, num_vars)?.iter() {
+            r.names.insert(*v, name);
+        }
+        let root = *new_idx.last().unwrap();
+        r.set_root(root);
+        Ok(r)
+    }
+}
+
+#[test]
+fn test_c2d_roundtrip() {
+    // (x1 && x2 && (x3 || !x3)) || (!x1 && (x2 || !x2) && x3)
+    let s = "nnf 11 12 3\nL 1\nL 2\nL 3\nL -3\nO 3 2 2 3\nA 3 0 1 4\nL -1\nL -2\nO 2 2 1 7\n\
+             A 3 6 8 2\nO 1 2 5 9\n";
+    let nnf = Nnf::from_c2d(s).unwrap();
+    assert!(nnf.is_sat());
+    let weights: HashMap<VarLabel, (usize, usize)> =
+        (0..3).map(|v| (VarLabel::new(v), (1, 1))).collect();
+    assert_eq!(nnf.wmc(&weights), 4);
+    let mut assgn = HashMap::new();
+    assgn.insert(VarLabel::new(0), false);
+    assgn.insert(VarLabel::new(1), true);
+    assgn.insert(VarLabel::new(2), true);
+    assert!(nnf.eval(&assgn));
+    assgn.insert(VarLabel::new(2), false);
+    assert!(!nnf.eval(&assgn));
+    assert_eq!(nnf.to_c2d(), s);
+}
+
+#[test]
+#[should_panic(expected = "variable 2 is missing")]
+fn test_eval_missing_var() {
+    let nnf = Nnf::from_c2d("nnf 3 2 3\nL 1\nL 3\nA 2 0 1\n").unwrap();
+    let assgn: HashMap<VarLabel, bool> = vec![(VarLabel::new(0), true)].into_iter().collect();
+    nnf.eval(&assgn);
+}
+
+#[test]
+fn test_c2d_errors() {
+    assert_eq!(Nnf::from_c2d("").unwrap_err().line, 1);
+    assert_eq!(Nnf::from_c2d("c comment\nsdd 1 0 1\nL 1\n").unwrap_err().line, 2);
+    // node count does not match the header
+    assert_eq!(Nnf::from_c2d("nnf 2 0 1\nL 1\n").unwrap_err().line, 1);
+    // child defined after its parent
+    assert_eq!(Nnf::from_c2d("nnf 2 1 1\nA 1 1\nL 1\n").unwrap_err().line, 2);
+    assert_eq!(Nnf::from_c2d("nnf 2 1 1\nL 0\nA 1 0\n").unwrap_err().line, 2);
+    assert_eq!(Nnf::from_c2d("nnf 1 0 1\nX 1\n").unwrap_err().line, 2);
+    // literals and decision variables beyond the header's variable count
+    assert_eq!(Nnf::from_c2d("nnf 1 0 1\nL -2\n").unwrap_err().line, 2);
+    assert_eq!(Nnf::from_c2d("nnf 2 1 1\nL 1\nO 3 1 0\n").unwrap_err().line, 3);
+    assert_eq!(Nnf::from_c2d("nnf 1 0 1\nL -9223372036854775808\n").unwrap_err().line, 2);
+}
@@ -0,0 +1,264 @@
+//! Linear pseudo-Boolean constraints `w_1 l_1 + ... + w_n l_n <op> k` over
+//! literals, and a reader for the OPB format.
+
+use repr::var_label::{Literal, VarLabel, MAX_VAR_SIZE};
+use serialize::ParseError;
+use std::collections::HashMap;
+extern crate quickcheck;
+use self::quickcheck::{Arbitrary, Gen};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PbOp {
+    Le,
+    Ge,
+    Eq,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PbConstraint {
+    pub terms: Vec<(Literal, i64)>,
+    pub op: PbOp,
+    pub k: i64,
+}
+
+impl PbConstraint {
+    pub fn new(terms: Vec<(Literal, i64)>, op: PbOp, k: i64) -> PbConstraint {
+        PbConstraint {
+            terms: terms,
+            op: op,
+            k: k,
+        }
+    }
+
+    /// `vars` has at most `k` true variables
+    pub fn at_most(vars: &[VarLabel], k: usize) -> PbConstraint {
+        PbConstraint::cardinality(vars, PbOp::Le, k)
+    }
+
+    /// `vars` has at least `k` true variables
+    pub fn at_least(vars: &[VarLabel], k: usize) -> PbConstraint {
+        PbConstraint::cardinality(vars, PbOp::Ge, k)
+    }
+
+    /// `vars` has exactly `k` true variables
+    pub fn exactly(vars: &[VarLabel], k: usize) -> PbConstraint {
+        PbConstraint::cardinality(vars, PbOp::Eq, k)
+    }
+
+    fn cardinality(vars: &[VarLabel], op: PbOp, k: usize) -> PbConstraint {
+        let terms = vars.iter().map(|v| (Literal::new(*v, true), 1)).collect();
+        PbConstraint::new(terms, op, k as i64)
+    }
+
+    /// The value of the left-hand side under `assgn`
+    fn sum(&self, assgn: &HashMap<VarLabel, bool>) -> i64 {
+        self.terms
+            .iter()
+            .filter(|&&(l, _)| assgn[&l.get_label()] == l.get_polarity())
+            .map(|&(_, w)| w)
+            .sum()
+    }
+
+    pub fn eval(&self, assgn: &HashMap<VarLabel, bool>) -> bool {
+        let s = self.sum(assgn);
+        match self.op {
+            PbOp::Le => s <= self.k,
+            PbOp::Ge => s >= self.k,
+            PbOp::Eq => s == self.k,
+        }
+    }
+
+    /// Rewrites `sum <= k` (or `sum >= k`, if `ge` is set) into an equivalent
+    /// `sum <= k'` with at most one literal per variable, each with a positive
+    /// weight; the terms are sorted by variable
+    pub fn normalize_le(&self, ge: bool) -> (Vec<(Literal, u64)>, i64) {
+        // `sum >= k` is `-sum <= -k`
+        let sign = if ge { -1 } else { 1 };
+        let mut k = sign * self.k;
+        // the weights of each variable's positive and negative literal
+        let mut coeffs: HashMap<VarLabel, (i64, i64)> = HashMap::new();
+        for &(l, w) in self.terms.iter() {
+            let e = coeffs.entry(l.get_label()).or_insert((0, 0));
+            if l.get_polarity() {
+                e.0 += sign * w;
+            } else {
+                e.1 += sign * w;
+            }
+        }
+        let mut terms = Vec::new();
+        for (v, (pos, neg)) in coeffs.into_iter() {
+            // pos x + neg !x == neg + (pos - neg) x, and a negative weight
+            // w x == w + (-w) !x
+            let (w, lit) = if pos >= neg {
+                k -= neg;
+                (pos - neg, Literal::new(v, true))
+            } else {
+                k -= pos;
+                (neg - pos, Literal::new(v, false))
+            };
+            if w != 0 {
+                terms.push((lit, w as u64));
+            }
+        }
+        terms.sort_by_key(|&(l, _)| l.get_label().value());
+        (terms, k)
+    }
+}
+
+/// The contents of an OPB file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Opb {
+    /// the terms of the `min:` objective, if there is one
+    pub objective: Option<Vec<(Literal, i64)>>,
+    pub constraints: Vec<PbConstraint>,
+    pub num_vars: usize,
+}
+
+impl Opb {
+    /// Reads a linear OPB file: `*` lines are comments, and every other
+    /// statement is `min: <terms> ;` or `<terms> <op> <k> ;`, where a term is a
+    /// weight followed by a variable `x<n>` or its negation `~x<n>`
+    pub fn from_opb(s: &str) -> Result<Opb, ParseError> {
+        let mut objective = None;
+        let mut constraints = Vec::new();
+        let mut num_vars = 0;
+        for (i, l) in s.lines().enumerate() {
+            let line = i + 1;
+            let l = l.trim();
+            if l.is_empty() || l.starts_with('*') {
+                continue;
+            }
+            if !l.ends_with(';') {
+                return Err(ParseError::new(line, "expected `;` at the end of the line"));
+            }
+            let mut toks: Vec<&str> = l[..l.len() - 1].split_whitespace().collect();
+            let is_objective = toks.first() == Some(&"min:");
+            if is_objective {
+                if objective.is_some() {
+                    return Err(ParseError::new(line, "duplicate objective"));
+                }
+                toks.remove(0);
+            }
+            let (op, k) = if is_objective {
+                (None, 0)
+            } else {
+                if toks.len() < 2 {
+                    return Err(ParseError::new(line, "expected `<terms> <op> <k> ;`"));
+                }
+                let k = toks.pop().unwrap();
+                let k: i64 = k
+                    .parse()
+                    .map_err(|_| ParseError::new(line, &format!("invalid number `{}`", k)))?;
+                let op = match toks.pop().unwrap() {
+                    "<=" => PbOp::Le,
+                    ">=" => PbOp::Ge,
+                    "=" => PbOp::Eq,
+                    t => {
+                        let msg = format!("expected `<=`, `>=` or `=`, found `{}`", t);
+                        return Err(ParseError::new(line, &msg));
+                    }
+                };
+                (Some(op), k)
+            };
+            if toks.len() % 2 != 0 {
+                let msg = "expected a weight and a single variable in each term";
+                return Err(ParseError::new(line, msg));
+            }
+            let mut terms = Vec::new();
+            for t in toks.chunks(2) {
+                let w: i64 = t[0]
+                    .parse()
+                    .map_err(|_| ParseError::new(line, &format!("invalid weight `{}`", t[0])))?;
+                let (polarity, name) = if t[1].starts_with('~') {
+                    (false, &t[1][1..])
+                } else {
+                    (true, t[1])
+                };
+                let v: usize = if name.starts_with('x') {
+                    name[1..].parse().unwrap_or(0)
+                } else {
+                    0
+                };
+                if v == 0 || v > MAX_VAR_SIZE {
+                    let msg = format!("invalid variable `{}`", t[1]);
+                    return Err(ParseError::new(line, &msg));
+                }
+                num_vars = num_vars.max(v);
+                terms.push((Literal::new(VarLabel::new(v as u64 - 1), polarity), w));
+            }
+            match op {
+                None => objective = Some(terms),
+                Some(op) => constraints.push(PbConstraint::new(terms, op, k)),
+            }
+        }
+        Ok(Opb {
+            objective: objective,
+            constraints: constraints,
+            num_vars: num_vars,
+        })
+    }
+}
+
+impl Arbitrary for PbConstraint {
+    fn arbitrary(g: &mut Gen) -> PbConstraint {
+        let num_vars = (u64::arbitrary(g) % 6) + 1;
+        let num_terms = (usize::arbitrary(g) % 6) + 1;
+        let terms = (0..num_terms)
+            .map(|_| {
+                let l = Literal::new(
+                    VarLabel::new(u64::arbitrary(g) % num_vars),
+                    bool::arbitrary(g),
+                );
+                (l, (i64::arbitrary(g) % 7) - 2)
+            })
+            .collect();
+        let op = *g.choose(&[PbOp::Le, PbOp::Ge, PbOp::Eq]).unwrap();
+        PbConstraint::new(terms, op, (i64::arbitrary(g) % 10) - 2)
+    }
+}
+
+#[cfg(test)]
+mod test_pb {
+    use super::*;
+
+    #[test]
+    fn test_opb() {
+        let s = "* #variable= 3 #constraint= 2\n\
+                 min: +1 x1 +2 x2 ;\n\
+                 +1 x1 -2 ~x3 >= -1 ;\n\
+                 \n\
+                 +3 x2 +1 x3 = 3 ;\n";
+        let opb = Opb::from_opb(s).unwrap();
+        let lit = |v: u64, p: bool| Literal::new(VarLabel::new(v), p);
+        assert_eq!(opb.num_vars, 3);
+        assert_eq!(
+            opb.objective,
+            Some(vec![(lit(0, true), 1), (lit(1, true), 2)])
+        );
+        assert_eq!(
+            opb.constraints,
+            vec![
+                PbConstraint::new(vec![(lit(0, true), 1), (lit(2, false), -2)], PbOp::Ge, -1),
+                PbConstraint::new(vec![(lit(1, true), 3), (lit(2, true), 1)], PbOp::Eq, 3),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_opb_errors() {
+        let cases = [
+            ("+1 x1 >= 1\n", 1, "expected `;`"),
+            ("* c\n+1 x1 > 1 ;\n", 2, "expected `<=`"),
+            ("+1 x1 x2 >= 1 ;\n", 1, "a weight and a single variable"),
+            ("+1 y1 >= 1 ;\n", 1, "invalid variable"),
+            ("+1 x0 >= 1 ;\n", 1, "invalid variable"),
+            ("a x1 >= 1 ;\n", 1, "invalid weight"),
+            ("min: +1 x1 ;\nmin: +1 x2 ;\n", 2, "duplicate objective"),
+        ];
+        for &(s, line, msg) in cases.iter() {
+            let e = Opb::from_opb(s).unwrap_err();
+            assert_eq!(e.line, line, "{}", s);
+            assert!(e.msg.contains(msg), "{}: {}", s, e.msg);
+        }
+    }
+}
@@ -1,6 +1,7 @@
 //! A generic data structure for tracking variable labels throughout the library
 use std::mem;
 use std::fmt;
+use std::collections::HashMap;
 extern crate quickcheck;
 use self::quickcheck::{Arbitrary, Gen};
 
@@ -67,4 +68,102 @@ impl Arbitrary for Literal {
         let varlbl = u64::arbitrary(g) % 16;
         Literal::new(VarLabel::new(varlbl), bool::arbitrary(g))
     }
-}
\ No newline at end of file
+}
+
+/// A symbol table between variable names and labels; every name belongs to
+/// at most one variable and every variable has at most one name
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VarNames {
+    by_name: HashMap<String, VarLabel>,
+    by_label: HashMap<VarLabel, String>,
+}
+
+impl VarNames {
+    pub fn new() -> VarNames {
+        VarNames::default()
+    }
+
+    /// True if `name` can name a variable: it is nonempty and has no
+    /// whitespace, so that every file format can store it
+    pub fn is_valid_name(name: &str) -> bool {
+        !name.is_empty() && !name.contains(char::is_whitespace)
+    }
+
+    /// Names `lbl`, replacing its previous name; panics if `name` is not a
+    /// valid name or already belongs to a different variable
+    pub fn insert(&mut self, lbl: VarLabel, name: &str) {
+        assert!(VarNames::is_valid_name(name), "`{}` is not a valid variable name", name);
+        match self.by_name.get(name) {
+            Some(l) if *l != lbl => panic!("`{}` already names variable {}", name, l.value()),
+            _ => (),
+        }
+        if let Some(old) = self.by_label.insert(lbl, String::from(name)) {
+            self.by_name.remove(&old);
+        }
+        self.by_name.insert(String::from(name), lbl);
+    }
+
+    /// Adds the valid names in `names` whose variable is unnamed and whose name
+    /// is unused, skipping the rest
+    pub fn merge(&mut self, names: &HashMap<VarLabel, String>) {
+        for (lbl, name) in names.iter() {
+            if VarNames::is_valid_name(name)
+                && !self.by_label.contains_key(lbl)
+                && !self.by_name.contains_key(name)
+            {
+                self.insert(*lbl, name);
+            }
+        }
+    }
+
+    /// The variable named `name`
+    pub fn label(&self, name: &str) -> Option<VarLabel> {
+        self.by_name.get(name).cloned()
+    }
+
+    /// The name of `lbl`
+    pub fn name(&self, lbl: VarLabel) -> Option<&str> {
+        self.by_label.get(&lbl).map(|s| s.as_str())
+    }
+
+    /// The name of `lbl`, or its number if it has none
+    pub fn display(&self, lbl: VarLabel) -> String {
+        match self.by_label.get(&lbl) {
+            Some(n) => n.clone(),
+            None => lbl.value().to_string(),
+        }
+    }
+
+    /// The names of the named variables
+    pub fn as_map(&self) -> &HashMap<VarLabel, String> {
+        &self.by_label
+    }
+
+    pub fn len(&self) -> usize {
+        self.by_label.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_label.is_empty()
+    }
+}
+
+#[test]
+fn var_names_basics() {
+    let mut names = VarNames::new();
+    names.insert(VarLabel::new(3), "a");
+    names.insert(VarLabel::new(4), "b");
+    // renaming frees the old name
+    names.insert(VarLabel::new(3), "c");
+    assert_eq!(names.label("a"), None);
+    assert_eq!(names.label("c"), Some(VarLabel::new(3)));
+    assert_eq!(names.name(VarLabel::new(4)), Some("b"));
+    assert_eq!(names.display(VarLabel::new(5)), "5");
+    assert_eq!(names.len(), 2);
+}
+
+#[test]
+#[should_panic(expected = "is not a valid variable name")]
+fn var_names_reject_line_breaks() {
+    VarNames::new().insert(VarLabel::new(0), "a\nb");
+}
@@ -0,0 +1,312 @@
+//! A compact, versioned binary format for saving a set of named BDDs together
+//! with the variable order of their manager.
+//!
+//! All integers are little-endian `u64`s. A file consists of:
+//!   - the magic bytes `RSDDBDD` followed by a single format version byte
+//!   - the variable order: its length followed by the variable at each
+//!     position
+//!   - the nodes, children before parents: their count followed by a `(var,
+//!     low, high)` triple for each node
+//!   - the roots: their count followed by a name (its length and UTF-8 bytes)
+//!     and an edge for each root
+//!   - the manager's variable names: their count followed by a variable and
+//!     a name for each (since version 2)
+//!
+//! An edge is `(idx << 1) | compl`, where `idx` 0 is the true node and `idx`
+//! `i > 0` is the `i`-th stored node.
+
+use manager::rsbdd_manager::BddManager;
+use manager::var_order::VarOrder;
+use repr::bdd::BddPtr;
+use repr::var_label::{VarLabel, MAX_VAR_SIZE};
+use std::collections::HashMap;
+use std::io;
+use std::io::{Read, Write};
+
+const MAGIC: &'static [u8] = b"RSDDBDD";
+const VERSION: u8 = 2;
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn write_u64<W: Write>(w: &mut W, v: u64) -> io::Result<()> {
+    w.write_all(&v.to_le_bytes())
+}
+
+fn read_u64<R: Read>(r: &mut R) -> io::Result<u64> {
+    let mut buf = [0; 8];
+    r.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn write_str<W: Write>(w: &mut W, s: &str) -> io::Result<()> {
+    write_u64(w, s.len() as u64)?;
+    w.write_all(s.as_bytes())
+}
+
+fn read_str<R: Read>(r: &mut R) -> io::Result<String> {
+    let len = read_u64(r)?;
+    let mut s = Vec::new();
+    r.take(len).read_to_end(&mut s)?;
+    if s.len() as u64 != len {
+        return Err(invalid("truncated name"));
+    }
+    String::from_utf8(s).map_err(|_| invalid("name is not UTF-8"))
+}
+
+/// Writes the BDDs in `roots`, which live in `man`, to `w`
+pub fn write_bdds<W: Write>(
+    man: &BddManager,
+    roots: &[(String, BddPtr)],
+    w: &mut W,
+) -> io::Result<()> {
+    /// assigns indices to the nodes below `ptr` in post-order
+    fn number(
+        man: &BddManager,
+        ptr: BddPtr,
+        idx: &mut HashMap<BddPtr, u64>,
+        nodes: &mut Vec<BddPtr>,
+    ) {
+        if ptr.is_const() {
+            return;
+        }
+        let reg = ptr.regular();
+        if idx.contains_key(&reg) {
+            return;
+        }
+        number(man, man.low(reg), idx, nodes);
+        number(man, man.high(reg), idx, nodes);
+        nodes.push(reg);
+        idx.insert(reg, nodes.len() as u64);
+    }
+
+    fn edge(ptr: BddPtr, idx: &HashMap<BddPtr, u64>) -> u64 {
+        if ptr.is_const() {
+            if ptr.is_true() {
+                0
+            } else {
+                1
+            }
+        } else {
+            (idx[&ptr.regular()] << 1) | (if ptr.is_compl() { 1 } else { 0 })
+        }
+    }
+
+    w.write_all(MAGIC)?;
+    w.write_all(&[VERSION])?;
+
+    let order = man.get_order();
+    write_u64(w, order.len() as u64)?;
+    for pos in 0..order.len() {
+        write_u64(w, order.var_at_pos(pos).value())?;
+    }
+
+    let mut idx = HashMap::new();
+    let mut nodes = Vec::new();
+    for &(_, ref ptr) in roots.iter() {
+        number(man, *ptr, &mut idx, &mut nodes);
+    }
+    write_u64(w, nodes.len() as u64)?;
+    for n in nodes.iter() {
+        write_u64(w, man.topvar(*n).value())?;
+        write_u64(w, edge(man.low(*n), &idx))?;
+        write_u64(w, edge(man.high(*n), &idx))?;
+    }
+
+    write_u64(w, roots.len() as u64)?;
+    for &(ref name, ref ptr) in roots.iter() {
+        write_str(w, name)?;
+        write_u64(w, edge(*ptr, &idx))?;
+    }
+
+    let mut names: Vec<(&VarLabel, &String)> = man.names().as_map().iter().collect();
+    names.sort_by_key(|&(v, _)| v.value());
+    write_u64(w, names.len() as u64)?;
+    for (v, name) in names.into_iter() {
+        write_u64(w, v.value())?;
+        write_str(w, name)?;
+    }
+    Ok(())
+}
+
+/// Reads the magic bytes, version, and variable order
+fn read_header<R: Read>(r: &mut R) -> io::Result<(u8, Vec<VarLabel>)> {
+    let mut magic = [0; 8];
+    r.read_exact(&mut magic)?;
+    if &magic[0..MAGIC.len()] != MAGIC {
+        return Err(invalid("not an rsdd BDD file"));
+    }
+    let version = magic[MAGIC.len()];
+    if version == 0 || version > VERSION {
+        return Err(invalid("unsupported BDD file version"));
+    }
+    let num_vars = read_u64(r)?;
+    if num_vars > MAX_VAR_SIZE as u64 {
+        return Err(invalid("too many variables"));
+    }
+    let mut seen = vec![false; num_vars as usize];
+    let mut order = Vec::new();
+    for _ in 0..num_vars {
+        let v = read_u64(r)?;
+        if v >= num_vars || seen[v as usize] {
+            return Err(invalid("variable order is not a permutation"));
+        }
+        seen[v as usize] = true;
+        order.push(VarLabel::new(v));
+    }
+    Ok((version, order))
+}
+
+/// Reads the nodes and roots, rebuilding them in `man`, and adds the variable
+/// names to the manager's names where they do not clash with existing ones
+fn read_body<R: Read>(
+    man: &mut BddManager,
+    version: u8,
+    r: &mut R,
+) -> io::Result<Vec<(String, BddPtr)>> {
+    fn edge(nodes: &[BddPtr], e: u64) -> io::Result<BddPtr> {
+        let idx = (e >> 1) as usize;
+        if idx > nodes.len() {
+            return Err(invalid("edge to a node which is not yet defined"));
+        }
+        let ptr = if idx == 0 {
+            BddPtr::true_node()
+        } else {
+            nodes[idx - 1]
+        };
+        Ok(if e & 1 == 1 { ptr.neg() } else { ptr })
+    }
+
+    let num_vars = man.get_order().len() as u64;
+    let num_nodes = read_u64(r)?;
+    let mut nodes: Vec<BddPtr> = Vec::new();
+    for _ in 0..num_nodes {
+        let var = read_u64(r)?;
+        if var >= num_vars {
+            return Err(invalid("variable is not in the manager"));
+        }
+        let lbl = VarLabel::new(var);
+        let low = edge(&nodes, read_u64(r)?)?;
+        let high = edge(&nodes, read_u64(r)?)?;
+        // the node can be inserted directly if it respects the manager's
+        // order; otherwise it must be rebuilt
+        let in_order = |p: BddPtr| p.is_const() || man.get_order().lt(lbl, p.label());
+        let ptr = if in_order(low) && in_order(high) {
+            man.mk_node(lbl, low, high)
+        } else {
+            let v = man.var(lbl, true);
+            man.ite(v, high, low)
+        };
+        nodes.push(ptr);
+    }
+
+    let num_roots = read_u64(r)?;
+    let mut roots = Vec::new();
+    for _ in 0..num_roots {
+        let name = read_str(r)?;
+        roots.push((name, edge(&nodes, read_u64(r)?)?));
+    }
+
+    if version >= 2 {
+        let num_names = read_u64(r)?;
+        let mut names = HashMap::new();
+        for _ in 0..num_names {
+            let var = read_u64(r)?;
+            if var >= num_vars {
+                return Err(invalid("named variable is not in the manager"));
+            }
+            names.insert(VarLabel::new(var), read_str(r)?);
+        }
+        man.names_mut().merge(&names);
+    }
+    Ok(roots)
+}
+
+/// Reads a set of named BDDs into a fresh manager with the stored variable
+/// order and variable names
+pub fn read_bdds<R: Read>(r: &mut R) -> io::Result<(BddManager, Vec<(String, BddPtr)>)> {
+    let (version, order) = read_header(r)?;
+    let mut man = BddManager::new(VarOrder::new(order));
+    let roots = read_body(&mut man, version, r)?;
+    Ok((man, roots))
+}
+
+/// Reads a set of named BDDs into the existing manager `man`, which may have a
+/// different variable order but must contain every stored variable
+pub fn read_bdds_into<R: Read>(
+    man: &mut BddManager,
+    r: &mut R,
+) -> io::Result<Vec<(String, BddPtr)>> {
+    let (version, order) = read_header(r)?;
+    if order.len() > man.get_order().len() {
+        return Err(invalid("the manager has fewer variables than the file"));
+    }
+    read_body(man, version, r)
+}
+
+#[cfg(test)]
+mod test_bdd_binary {
+    use super::*;
+    use rand::{Rng, SeedableRng, StdRng};
+    use repr::cnf::Cnf;
+
+    quickcheck! {
+        fn roundtrip(c1: Cnf, c2: Cnf, seed: usize) -> bool {
+            let mut man = BddManager::new_default_order(16);
+            let f = man.from_cnf(&c1);
+            let g = man.from_cnf(&c2);
+            let roots = vec![
+                (String::from("f"), f),
+                (String::from("not g"), g.neg()),
+                (String::from("false"), man.false_ptr()),
+            ];
+            let mut buf = Vec::new();
+            write_bdds(&man, &roots, &mut buf).unwrap();
+
+            // load into a fresh manager
+            let (mut fresh, loaded) = read_bdds(&mut &buf[..]).unwrap();
+            let f2 = fresh.from_cnf(&c1);
+            let g2 = fresh.from_cnf(&c2);
+            let fresh_ok = loaded[0] == (String::from("f"), f2)
+                && fresh.eq_bdd(loaded[1].1, g2.neg())
+                && loaded[1].0 == "not g"
+                && fresh.is_false(loaded[2].1);
+
+            // load into an existing manager with a shuffled order
+            let mut rng: StdRng = SeedableRng::from_seed(&[seed][..]);
+            let mut order: Vec<VarLabel> = (0..16).map(|x| VarLabel::new(x)).collect();
+            rng.shuffle(&mut order);
+            let mut other = BddManager::new(VarOrder::new(order));
+            let loaded = read_bdds_into(&mut other, &mut &buf[..]).unwrap();
+            let f3 = other.from_cnf(&c1);
+            let g3 = other.from_cnf(&c2);
+            fresh_ok && other.eq_bdd(loaded[0].1, f3) && other.eq_bdd(loaded[1].1, g3.neg())
+        }
+    }
+
+    #[test]
+    fn names_roundtrip() {
+        let mut man = BddManager::new_default_order(0);
+        let f = man.parse_expr("(a -> b) & c").unwrap();
+        let mut buf = Vec::new();
+        write_bdds(&man, &[(String::from("f"), f)], &mut buf).unwrap();
+        let (fresh, loaded) = read_bdds(&mut &buf[..]).unwrap();
+        assert_eq!(fresh.names(), man.names());
+        assert_eq!(fresh.print_bdd(loaded[0].1), man.print_bdd(f));
+    }
+
+    #[test]
+    fn rejects_bad_header() {
+        let man = BddManager::new_default_order(2);
+        let mut buf = Vec::new();
+        write_bdds(&man, &[], &mut buf).unwrap();
+        buf[MAGIC.len()] = VERSION + 1;
+        assert!(read_bdds(&mut &buf[..]).is_err());
+        assert!(read_bdds(&mut &b"not a bdd file"[..]).is_err());
+        // truncated files are reported as errors
+        let mut buf = Vec::new();
+        write_bdds(&man, &[], &mut buf).unwrap();
+        assert!(read_bdds(&mut &buf[..buf.len() - 1]).is_err());
+    }
+}
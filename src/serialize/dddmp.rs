@@ -0,0 +1,525 @@
+//! Reading and writing BDDs in CUDD's DDDMP text format (mode `A`).
+//!
+//! A DDDMP file has a header of `.key value` lines followed by the nodes
+//! between `.nodes` and `.end`. Each node line is `id [info] var then else`,
+//! children before parents, where:
+//!   - `var` is the position of the node's variable in the `.ids` list of
+//!     support variables, and `info` is extra information selected by
+//!     `.varinfo` (it is absent for `.varinfo 3`)
+//!   - node 1 is the constant true, written `1 T 1 0 0`
+//!   - a negative `else` id is a complemented edge; `then` edges are never
+//!     complemented
+//!
+//! The roots are listed in `.rootids`, where a negative id is also a
+//! complemented edge. The variable order is given by `.permids`, the position
+//! of each support variable in the order.
+
+use manager::rsbdd_manager::BddManager;
+use manager::var_order::VarOrder;
+use repr::bdd::BddPtr;
+use repr::var_label::{VarLabel, MAX_VAR_SIZE};
+use serialize::ParseError;
+use std::collections::HashMap;
+
+/// Checks that `name` can be written as a single DDDMP token
+fn check_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || name.chars().any(char::is_whitespace) {
+        Err(format!("name `{}` is empty or contains whitespace", name))
+    } else {
+        Ok(())
+    }
+}
+
+/// Writes the BDDs in `roots`, which live in `man`, as a DDDMP file for a
+/// diagram called `name`. The variables are written under their names in
+/// `names`, if given, or else in the manager's names, if it has any; unnamed
+/// variables are called `x<n>`. Returns an error if a name is empty, contains
+/// whitespace, or is shared by two variables.
+pub fn write_dddmp(
+    man: &BddManager,
+    name: &str,
+    roots: &[(String, BddPtr)],
+    names: Option<&HashMap<VarLabel, String>>,
+) -> Result<String, String> {
+    /// numbers the nodes below `ptr` in post-order, returning the signed id of
+    /// the edge to `ptr`; a stored node whose then-edge would be complemented
+    /// is written negated, with a negative id
+    fn number(
+        man: &BddManager,
+        ptr: BddPtr,
+        ids: &mut HashMap<BddPtr, i64>,
+        nodes: &mut Vec<(VarLabel, i64, i64)>,
+    ) -> i64 {
+        if ptr.is_true() {
+            return 1;
+        } else if ptr.is_false() {
+            return -1;
+        }
+        let reg = ptr.regular();
+        let id = match ids.get(&reg) {
+            Some(id) => *id,
+            None => {
+                let lo = number(man, man.low(reg), ids, nodes);
+                let hi = number(man, man.high(reg), ids, nodes);
+                let (lo, hi, sign) = if hi < 0 { (-lo, -hi, -1) } else { (lo, hi, 1) };
+                nodes.push((man.topvar(reg), hi, lo));
+                // the terminal is node 1
+                let id = sign * (nodes.len() as i64 + 1);
+                ids.insert(reg, id);
+                id
+            }
+        };
+        if ptr.is_compl() {
+            -id
+        } else {
+            id
+        }
+    }
+
+    let mut ids = HashMap::new();
+    let mut nodes = Vec::new();
+    let root_ids: Vec<i64> = roots
+        .iter()
+        .map(|&(_, ptr)| number(man, ptr, &mut ids, &mut nodes))
+        .collect();
+
+    let mut support: Vec<VarLabel> = nodes.iter().map(|&(v, _, _)| v).collect();
+    support.sort_by_key(|v| v.value());
+    support.dedup();
+    let support_pos: HashMap<VarLabel, usize> =
+        support.iter().enumerate().map(|(i, v)| (*v, i)).collect();
+    let order = man.get_order();
+
+    check_name(name)?;
+    for &(ref n, _) in roots.iter() {
+        check_name(n)?;
+    }
+
+    let join = |v: Vec<String>| v.join(" ");
+    let mut r = String::new();
+    r.push_str(".ver DDDMP-2.0\n.mode A\n.varinfo 0\n");
+    r.push_str(&format!(".dd {}\n", name));
+    r.push_str(&format!(".nnodes {}\n", nodes.len() + 1));
+    r.push_str(&format!(".nvars {}\n", order.len()));
+    r.push_str(&format!(".nsuppvars {}\n", support.len()));
+    let names = match names {
+        None if !man.names().is_empty() => Some(man.names().as_map()),
+        n => n,
+    };
+    match names {
+        Some(names) => {
+            let name_of = |v: VarLabel| match names.get(&v) {
+                Some(n) => n.clone(),
+                None => format!("x{}", v.value()),
+            };
+            let mut seen = HashMap::new();
+            for p in 0..order.len() {
+                let v = order.var_at_pos(p);
+                let n = name_of(v);
+                check_name(&n)?;
+                match seen.insert(n.clone(), v) {
+                    Some(w) => {
+                        return Err(format!(
+                            "variables {} and {} are both named `{}`",
+                            w.value(),
+                            v.value(),
+                            n
+                        ))
+                    }
+                    None => (),
+                }
+            }
+            r.push_str(&format!(
+                ".suppvarnames {}\n",
+                join(support.iter().map(|v| name_of(*v)).collect())
+            ));
+            r.push_str(&format!(
+                ".orderedvarnames {}\n",
+                join((0..order.len()).map(|p| name_of(order.var_at_pos(p))).collect())
+            ));
+        }
+        None => (),
+    }
+    r.push_str(&format!(
+        ".ids {}\n",
+        join(support.iter().map(|v| v.value().to_string()).collect())
+    ));
+    r.push_str(&format!(
+        ".permids {}\n",
+        join(support.iter().map(|v| order.get(*v).to_string()).collect())
+    ));
+    r.push_str(&format!(".nroots {}\n", roots.len()));
+    r.push_str(&format!(
+        ".rootids {}\n",
+        join(root_ids.iter().map(|i| i.to_string()).collect())
+    ));
+    r.push_str(&format!(
+        ".rootnames {}\n",
+        join(roots.iter().map(|&(ref n, _)| n.clone()).collect())
+    ));
+    r.push_str(".nodes\n1 T 1 0 0\n");
+    for (i, &(v, hi, lo)) in nodes.iter().enumerate() {
+        r.push_str(&format!(
+            "{} {} {} {} {}\n",
+            i + 2,
+            v.value(),
+            support_pos[&v],
+            hi,
+            lo
+        ));
+    }
+    r.push_str(".end\n");
+    Ok(r)
+}
+
+/// The header fields of a DDDMP file which are needed to rebuild its BDDs
+struct Header {
+    nvars: usize,
+    /// the support variables and their positions in the order
+    ids: Vec<VarLabel>,
+    permids: Option<Vec<usize>>,
+    names: Option<Vec<String>>,
+    root_ids: Vec<i64>,
+    root_names: Option<Vec<String>>,
+    /// the line of `.nodes`
+    nodes_line: usize,
+}
+
+fn parse_num<T: ::std::str::FromStr>(line: usize, tok: &str) -> Result<T, ParseError> {
+    tok.parse()
+        .map_err(|_| ParseError::new(line, &format!("invalid number `{}`", tok)))
+}
+
+fn parse_list<T: ::std::str::FromStr>(line: usize, toks: &[&str]) -> Result<Vec<T>, ParseError> {
+    toks.iter().map(|t| parse_num(line, t)).collect()
+}
+
+/// Parses the header from the non-empty lines `lines`, stopping at `.nodes`
+fn parse_header<'a, I>(lines: &mut I) -> Result<Header, ParseError>
+where
+    I: Iterator<Item = (usize, Vec<&'a str>)>,
+{
+    let mut nvars = None;
+    let mut ids = None;
+    let mut permids = None;
+    let mut names = None;
+    let mut root_ids = None;
+    let mut root_names = None;
+    for (line, toks) in lines {
+        let args = &toks[1..];
+        match toks[0] {
+            ".ver" => {
+                if args.len() != 1 || !args[0].starts_with("DDDMP-") {
+                    return Err(ParseError::new(line, "not a DDDMP file"));
+                }
+            }
+            ".mode" => {
+                if args != ["A"] {
+                    return Err(ParseError::new(line, "only text (mode A) files are supported"));
+                }
+            }
+            ".nvars" => {
+                let n: usize = parse_num(line, args.get(0).unwrap_or(&""))?;
+                if n > MAX_VAR_SIZE {
+                    return Err(ParseError::new(line, "too many variables"));
+                }
+                nvars = Some(n);
+            }
+            ".ids" => {
+                let v: Vec<u64> = parse_list(line, args)?;
+                ids = Some(v.into_iter().map(VarLabel::new).collect());
+            }
+            ".permids" => permids = Some(parse_list(line, args)?),
+            ".suppvarnames" => names = Some(args.iter().map(|s| s.to_string()).collect()),
+            ".rootids" => root_ids = Some(parse_list(line, args)?),
+            ".rootnames" => root_names = Some(args.iter().map(|s| s.to_string()).collect()),
+            ".nodes" => {
+                let nvars = match nvars {
+                    Some(n) => n,
+                    None => return Err(ParseError::new(line, "missing `.nvars`")),
+                };
+                let ids: Vec<VarLabel> = match ids {
+                    Some(ids) => ids,
+                    None => return Err(ParseError::new(line, "missing `.ids`")),
+                };
+                let root_ids: Vec<i64> = match root_ids {
+                    Some(r) => r,
+                    None => return Err(ParseError::new(line, "missing `.rootids`")),
+                };
+                if ids.iter().any(|v| v.value() as usize >= nvars) {
+                    return Err(ParseError::new(line, "support variable out of range"));
+                }
+                let bad_len = |l: Option<usize>| l.map(|l| l != ids.len()).unwrap_or(false);
+                if bad_len(permids.as_ref().map(|p: &Vec<usize>| p.len()))
+                    || bad_len(names.as_ref().map(|n: &Vec<String>| n.len()))
+                {
+                    return Err(ParseError::new(line, "support lists have different lengths"));
+                }
+                if root_names.as_ref().map(|n: &Vec<String>| n.len() != root_ids.len()) == Some(true)
+                {
+                    return Err(ParseError::new(line, "`.rootnames` does not match `.rootids`"));
+                }
+                return Ok(Header {
+                    nvars: nvars,
+                    ids: ids,
+                    permids: permids,
+                    names: names,
+                    root_ids: root_ids,
+                    root_names: root_names,
+                    nodes_line: line,
+                });
+            }
+            // the remaining keys (`.dd`, `.nnodes`, `.orderedvarnames`, ...)
+            // are not needed to rebuild the BDDs
+            _ => (),
+        }
+    }
+    Err(ParseError::new(1, "missing `.nodes`"))
+}
+
+/// Reads the nodes and roots, rebuilding them in `man`
+fn parse_body<'a, I>(
+    man: &mut BddManager,
+    header: &Header,
+    lines: &mut I,
+) -> Result<Vec<(String, BddPtr)>, ParseError>
+where
+    I: Iterator<Item = (usize, Vec<&'a str>)>,
+{
+    let mut nodes: HashMap<u64, BddPtr> = HashMap::new();
+    let edge = |nodes: &HashMap<u64, BddPtr>, line: usize, e: i64| {
+        match nodes.get(&e.unsigned_abs()) {
+            Some(p) => Ok(if e < 0 { p.neg() } else { *p }),
+            None => Err(ParseError::new(line, "edge to a node which is not yet defined")),
+        }
+    };
+    let mut end = None;
+    for (line, toks) in lines {
+        if toks[0] == ".end" {
+            end = Some(line);
+            break;
+        }
+        let fields: Vec<&str> = match toks.len() {
+            5 => vec![toks[0], toks[2], toks[3], toks[4]],
+            4 => toks.clone(),
+            _ => return Err(ParseError::new(line, "malformed node")),
+        };
+        let id: i64 = parse_num(line, fields[0])?;
+        let var: usize = parse_num(line, fields[1])?;
+        let hi: i64 = parse_num(line, fields[2])?;
+        let lo: i64 = parse_num(line, fields[3])?;
+        if id <= 0 || nodes.contains_key(&id.unsigned_abs()) {
+            return Err(ParseError::new(line, "invalid node id"));
+        }
+        let ptr = if hi == 0 && lo == 0 {
+            // a terminal, whose value is in the variable field
+            if var == 0 {
+                man.false_ptr()
+            } else {
+                man.true_ptr()
+            }
+        } else {
+            let lbl = match header.ids.get(var) {
+                Some(l) => *l,
+                None => return Err(ParseError::new(line, "variable is not in the support")),
+            };
+            let low = edge(&nodes, line, lo)?;
+            let high = edge(&nodes, line, hi)?;
+            // the node can be inserted directly if it respects the manager's
+            // order; otherwise it must be rebuilt
+            let in_order = |p: BddPtr| p.is_const() || man.get_order().lt(lbl, p.label());
+            if in_order(low) && in_order(high) {
+                man.mk_node(lbl, low, high)
+            } else {
+                let v = man.var(lbl, true);
+                man.ite(v, high, low)
+            }
+        };
+        nodes.insert(id.unsigned_abs(), ptr);
+    }
+    let end = match end {
+        Some(l) => l,
+        None => return Err(ParseError::new(header.nodes_line, "missing `.end`")),
+    };
+    let mut roots = Vec::new();
+    for (i, r) in header.root_ids.iter().enumerate() {
+        let name = match header.root_names {
+            Some(ref n) => n[i].clone(),
+            None => i.to_string(),
+        };
+        roots.push((name, edge(&nodes, end, *r)?));
+    }
+    Ok(roots)
+}
+
+/// Splits `s` into the whitespace-separated tokens of each non-empty line,
+/// numbering lines from 1
+fn tokenize(s: &str) -> Vec<(usize, Vec<&str>)> {
+    s.lines()
+        .enumerate()
+        .map(|(i, l)| (i + 1, l.split_whitespace().collect::<Vec<&str>>()))
+        .filter(|&(_, ref toks)| !toks.is_empty())
+        .collect()
+}
+
+/// Reads a DDDMP file into a fresh manager, returning the manager, the named
+/// roots, and the names of the support variables (if the file has them), which
+/// are also registered in the manager's names. The support variables are
+/// placed in the stored order; the remaining variables fill the free positions
+/// in increasing order.
+pub fn read_dddmp(
+    s: &str,
+) -> Result<(BddManager, Vec<(String, BddPtr)>, HashMap<VarLabel, String>), ParseError> {
+    let lines = tokenize(s);
+    let mut iter = lines.into_iter();
+    let header = parse_header(&mut iter)?;
+
+    let mut order: Vec<Option<VarLabel>> = vec![None; header.nvars];
+    match header.permids {
+        Some(ref permids) => {
+            for (v, p) in header.ids.iter().zip(permids.iter()) {
+                if *p >= header.nvars || order[*p].is_some() {
+                    return Err(ParseError::new(header.nodes_line, "`.permids` is not a permutation"));
+                }
+                order[*p] = Some(*v);
+            }
+        }
+        None => (),
+    }
+    let placed: Vec<VarLabel> = order.iter().filter_map(|v| *v).collect();
+    let mut free = (0..header.nvars as u64)
+        .map(VarLabel::new)
+        .filter(|v| !placed.contains(v));
+    let order: Vec<VarLabel> = order
+        .into_iter()
+        .map(|v| v.unwrap_or_else(|| free.next().unwrap()))
+        .collect();
+
+    let mut man = BddManager::new(VarOrder::new(order));
+    let roots = parse_body(&mut man, &header, &mut iter)?;
+    let names = match header.names {
+        Some(ref n) => header.ids.iter().cloned().zip(n.iter().cloned()).collect(),
+        None => HashMap::new(),
+    };
+    man.names_mut().merge(&names);
+    Ok((man, roots, names))
+}
+
+/// Reads the BDDs of a DDDMP file into the existing manager `man`, which may
+/// have a different variable order but must contain every stored variable. The
+/// names of the support variables are added to the manager's names where they
+/// do not clash with existing ones.
+pub fn read_dddmp_into(man: &mut BddManager, s: &str) -> Result<Vec<(String, BddPtr)>, ParseError> {
+    let lines = tokenize(s);
+    let mut iter = lines.into_iter();
+    let header = parse_header(&mut iter)?;
+    if header.ids.iter().any(|v| v.value() as usize >= man.get_order().len()) {
+        return Err(ParseError::new(
+            header.nodes_line,
+            "the manager does not contain every support variable",
+        ));
+    }
+    let roots = parse_body(man, &header, &mut iter)?;
+    if let Some(ref n) = header.names {
+        let names = header.ids.iter().cloned().zip(n.iter().cloned()).collect();
+        man.names_mut().merge(&names);
+    }
+    Ok(roots)
+}
+
+#[cfg(test)]
+mod test_dddmp {
+    use super::*;
+    use rand::{Rng, SeedableRng, StdRng};
+    use repr::cnf::Cnf;
+
+    #[test]
+    fn read_cudd_dump() {
+        // x0 && !x2, stored by CUDD with x2 above x0; the root is complemented
+        let s = ".ver DDDMP-2.0\n.mode A\n.varinfo 0\n.dd f\n.nnodes 3\n.nvars 3\n\
+                 .nsuppvars 2\n.suppvarnames a c\n.ids 0 2\n.permids 1 0\n.nroots 1\n\
+                 .rootids -3\n.nodes\n1 T 1 0 0\n2 0 0 1 -1\n3 2 1 1 -2\n.end\n";
+        let (mut man, roots, names) = read_dddmp(s).unwrap();
+        assert_eq!(roots[0].0, "0");
+        assert_eq!(names[&VarLabel::new(2)], "c");
+        assert_eq!(man.get_order().var_at_pos(0), VarLabel::new(2));
+        let x0 = man.var(VarLabel::new(0), true);
+        let x2 = man.var(VarLabel::new(2), false);
+        let expected = man.and(x0, x2);
+        assert!(man.eq_bdd(roots[0].1, expected));
+    }
+
+    fn error_line(s: &str) -> usize {
+        match read_dddmp(s) {
+            Ok(_) => panic!("expected a parse error"),
+            Err(e) => e.line,
+        }
+    }
+
+    #[test]
+    fn reports_errors() {
+        assert_eq!(error_line(".ver DDDMP-2.0\n.mode B\n"), 2);
+        let s = ".ver DDDMP-2.0\n.nvars 1\n.ids 0\n.rootids 2\n.nodes\n1 T 1 0 0\n\
+                 2 0 0 1 -3\n.end\n";
+        assert_eq!(error_line(s), 7);
+        let s = ".ver DDDMP-2.0\n.nvars 1\n.ids 0\n.rootids 2\n.nodes\n1 T 1 0 0\n\
+                 2 0 0 1 -9223372036854775808\n.end\n";
+        assert_eq!(error_line(s), 7);
+        let s = ".ver DDDMP-2.0\n.nvars 1\n.ids 0\n.rootids 1\n.nodes\n1 T 1 0 0\n";
+        assert_eq!(error_line(s), 5);
+    }
+
+    #[test]
+    fn rejects_bad_names() {
+        let mut man = BddManager::new_default_order(3);
+        let x0 = man.var(VarLabel::new(0), true);
+        let roots = vec![(String::from("f"), x0)];
+        assert!(write_dddmp(&man, "d d", &roots, None).is_err());
+        let spaced = vec![(String::from("not g"), x0)];
+        assert!(write_dddmp(&man, "d", &spaced, None).is_err());
+        let names: HashMap<VarLabel, String> = vec![(VarLabel::new(0), String::from("a b"))]
+            .into_iter()
+            .collect();
+        assert!(write_dddmp(&man, "d", &roots, Some(&names)).is_err());
+        // the default name of x1 collides with the name given to x0
+        man.names_mut().insert(VarLabel::new(0), "x1");
+        let e = write_dddmp(&man, "d", &roots, None).unwrap_err();
+        assert!(e.contains("both named `x1`"), "{}", e);
+    }
+
+    quickcheck! {
+        fn dddmp_roundtrip(c1: Cnf, c2: Cnf, seed: usize) -> bool {
+            let mut rng: StdRng = SeedableRng::from_seed(&[seed][..]);
+            let mut order: Vec<VarLabel> = (0..10).map(|x| VarLabel::new(x)).collect();
+            rng.shuffle(&mut order);
+            let mut man = BddManager::new(VarOrder::new(order));
+            let f = man.from_cnf(&c1);
+            let g = man.from_cnf(&c2);
+            let roots = vec![
+                (String::from("f"), f),
+                (String::from("not_g"), g.neg()),
+                (String::from("false"), man.false_ptr()),
+            ];
+            let names: HashMap<VarLabel, String> =
+                (0..10).map(|v| (VarLabel::new(v), format!("v{}", v))).collect();
+            let s = write_dddmp(&man, "test", &roots, Some(&names)).unwrap();
+
+            // load into a fresh manager, which has the support in the same order
+            let (mut fresh, loaded, loaded_names) = read_dddmp(&s).unwrap();
+            let f2 = fresh.from_cnf(&c1);
+            let g2 = fresh.from_cnf(&c2);
+            let fresh_ok = fresh.eq_bdd(loaded[0].1, f2)
+                && fresh.eq_bdd(loaded[1].1, g2.neg())
+                && loaded[1].0 == "not_g"
+                && fresh.is_false(loaded[2].1)
+                && loaded_names.iter().all(|(v, n)| names[v] == *n);
+
+            // load into a manager with the default order
+            let mut other = BddManager::new_default_order(10);
+            let loaded = read_dddmp_into(&mut other, &s).unwrap();
+            let f3 = other.from_cnf(&c1);
+            let g3 = other.from_cnf(&c2);
+            fresh_ok && other.eq_bdd(loaded[0].1, f3) && other.eq_bdd(loaded[1].1, g3.neg())
+        }
+    }
+}
@@ -0,0 +1,430 @@
+//! Graphviz DOT export for decision diagrams.
+//!
+//! There is a single terminal node, true; false is drawn as a complemented
+//! edge to it. High edges are solid, low edges are dashed, and complemented
+//! edges have a hollow circle for an arrowhead.
+
+use manager::rsbdd_manager::BddManager;
+use manager::sdd_manager::SddManager;
+use repr::bdd::BddPtr;
+use repr::sdd::{SddPtr, VTree};
+use repr::var_label::VarLabel;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use util::btree::BTree;
+
+/// Escapes `s` for use inside a quoted DOT string
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// The label of `lbl`: its name in `names` if there is one, and otherwise its
+/// number
+fn var_name(names: Option<&HashMap<VarLabel, String>>, lbl: VarLabel) -> String {
+    match names.and_then(|m| m.get(&lbl)) {
+        Some(n) => escape(n),
+        None => format!("{}", lbl.value()),
+    }
+}
+
+/// The DOT id of `ptr`, ignoring its complement; `prefix` distinguishes the
+/// nodes of different BDD managers
+fn bdd_id(prefix: &str, ptr: BddPtr) -> String {
+    if ptr.is_const() {
+        format!("{}t", prefix)
+    } else {
+        format!("{}n{}", prefix, ptr.regular().raw())
+    }
+}
+
+/// An edge from the node `from` to `to`; `compl` marks complemented edges
+fn edge(from: &str, to: &str, low: bool, compl: bool) -> String {
+    let mut attrs = Vec::new();
+    if low {
+        attrs.push("style=dashed");
+    }
+    if compl {
+        attrs.push("arrowhead=odot");
+    }
+    if attrs.is_empty() {
+        format!("{} -> {};", from, to)
+    } else {
+        format!("{} -> {} [{}];", from, to, attrs.join(", "))
+    }
+}
+
+/// The complement flag of an edge to `ptr`; constants are complemented when
+/// false
+fn is_compl(ptr: BddPtr) -> bool {
+    if ptr.is_const() {
+        ptr.is_false()
+    } else {
+        ptr.is_compl()
+    }
+}
+
+/// Draws the nodes below `ptr` which are not in `seen`, appending their
+/// definitions and edges to `lines` and recording each node's id under its
+/// position in the variable order in `levels`. `label` names each variable.
+fn draw_bdd_nodes<F>(
+    man: &BddManager,
+    ptr: BddPtr,
+    prefix: &str,
+    label: &F,
+    seen: &mut HashSet<BddPtr>,
+    levels: &mut BTreeMap<usize, Vec<String>>,
+    lines: &mut Vec<String>,
+) where
+    F: Fn(VarLabel) -> String,
+{
+    if ptr.is_const() {
+        return;
+    }
+    let reg = ptr.regular();
+    if !seen.insert(reg) {
+        return;
+    }
+    let id = bdd_id(prefix, reg);
+    let var = man.topvar(reg);
+    lines.push(format!("{} [label=\"{}\"];", id, label(var)));
+    levels
+        .entry(man.get_order().get(var))
+        .or_insert_with(Vec::new)
+        .push(id.clone());
+    let (lo, hi) = (man.low(reg), man.high(reg));
+    lines.push(edge(&id, &bdd_id(prefix, hi), false, is_compl(hi)));
+    lines.push(edge(&id, &bdd_id(prefix, lo), true, is_compl(lo)));
+    draw_bdd_nodes(man, hi, prefix, label, seen, levels, lines);
+    draw_bdd_nodes(man, lo, prefix, label, seen, levels, lines);
+}
+
+/// Draws the BDDs in `roots`, which live in `man`, as a DOT graph. Shared nodes
+/// are drawn once and nodes are ranked by their variable's position in the
+/// order. Variables are labeled by their name in `names`, if given, or in the
+/// manager's names, and by their number otherwise.
+pub fn bdd_to_dot(
+    man: &BddManager,
+    roots: &[(String, BddPtr)],
+    names: Option<&HashMap<VarLabel, String>>,
+) -> String {
+    let names = names.or(Some(man.names().as_map()));
+    let label = |lbl: VarLabel| var_name(names, lbl);
+    let mut lines = Vec::new();
+    let mut levels = BTreeMap::new();
+    let mut seen = HashSet::new();
+    for &(_, ref ptr) in roots.iter() {
+        draw_bdd_nodes(man, *ptr, "", &label, &mut seen, &mut levels, &mut lines);
+    }
+
+    let mut r = String::from("digraph bdd {\n  node [shape=circle];\n");
+    r.push_str(&format!("  {} [label=\"T\", shape=box];\n", bdd_id("", BddPtr::true_node())));
+    for l in lines.iter() {
+        r.push_str(&format!("  {}\n", l));
+    }
+    for (_, ids) in levels.iter() {
+        r.push_str(&format!("  {{ rank=same; {}; }}\n", ids.join("; ")));
+    }
+    r.push_str(&format!("  {{ rank=sink; {}; }}\n", bdd_id("", BddPtr::true_node())));
+    for (i, &(ref name, ref ptr)) in roots.iter().enumerate() {
+        r.push_str(&format!("  r{} [label=\"{}\", shape=plaintext];\n", i, escape(name)));
+        r.push_str(&format!(
+            "  {}\n",
+            edge(&format!("r{}", i), &bdd_id("", *ptr), false, is_compl(*ptr))
+        ));
+    }
+    r.push_str("}\n");
+    r
+}
+
+/// The DOT id of the SDD `ptr`, ignoring its complement. BDD pointers are
+/// named after their vtree leaf, and constants refer to the terminal `t`.
+fn sdd_id(ptr: SddPtr) -> String {
+    if ptr.is_const() {
+        String::from("t")
+    } else if ptr.is_bdd() {
+        bdd_id(&format!("b{}_", ptr.vtree()), ptr.as_bdd_ptr())
+    } else {
+        format!("d{}_{}", ptr.vtree(), ptr.idx())
+    }
+}
+
+/// The complement flag of an edge to the SDD `ptr`
+fn sdd_is_compl(ptr: SddPtr) -> bool {
+    if ptr.is_const() {
+        ptr.is_false()
+    } else if ptr.is_bdd() {
+        is_compl(ptr.as_bdd_ptr())
+    } else {
+        ptr.is_compl()
+    }
+}
+
+/// Draws the SDDs in `roots`, which live in `man`, as a DOT graph. Each
+/// decision node is a circle labeled with its vtree index, pointing to a box
+/// for each of its (prime, sub) elements; constant primes and subs are written
+/// inside the box. The BDDs at the vtree leaves are drawn in a cluster for each
+/// leaf. Variables are labeled by their name in `names`, if given, or in the
+/// manager's names, and by their number otherwise.
+pub fn sdd_to_dot(
+    man: &SddManager,
+    roots: &[(String, SddPtr)],
+    names: Option<&HashMap<VarLabel, String>>,
+) -> String {
+    /// the BDD nodes drawn in the cluster of a vtree leaf
+    struct Cluster {
+        seen: HashSet<BddPtr>,
+        levels: BTreeMap<usize, Vec<String>>,
+        lines: Vec<String>,
+    }
+
+    fn helper(
+        man: &SddManager,
+        ptr: SddPtr,
+        names: Option<&HashMap<VarLabel, String>>,
+        seen: &mut HashSet<SddPtr>,
+        clusters: &mut BTreeMap<usize, Cluster>,
+        lines: &mut Vec<String>,
+    ) {
+        if ptr.is_const() {
+            return;
+        } else if ptr.is_bdd() {
+            let vnode = ptr.vtree();
+            let c = clusters.entry(vnode).or_insert_with(|| Cluster {
+                seen: HashSet::new(),
+                levels: BTreeMap::new(),
+                lines: Vec::new(),
+            });
+            let label = |lbl: VarLabel| var_name(names, man.bdd_to_sdd_label(vnode, lbl));
+            draw_bdd_nodes(
+                man.get_bdd_mgr(ptr),
+                ptr.as_bdd_ptr(),
+                &format!("b{}_", vnode),
+                &label,
+                &mut c.seen,
+                &mut c.levels,
+                &mut c.lines,
+            );
+            return;
+        }
+        let reg = if ptr.is_compl() { ptr.neg() } else { ptr };
+        if !seen.insert(reg) {
+            return;
+        }
+        let id = sdd_id(reg);
+        lines.push(format!("{} [label=\"{}\"];", id, reg.vtree()));
+        for (i, &(ref p, ref s)) in man.elements(reg).iter().enumerate() {
+            let elem = format!("{}_e{}", id, i);
+            let show = |x: SddPtr| {
+                if x.is_true() {
+                    "T"
+                } else if x.is_false() {
+                    "F"
+                } else {
+                    ""
+                }
+            };
+            lines.push(format!(
+                "{} [label=\"<p> {}|<s> {}\", shape=record];",
+                elem,
+                show(*p),
+                show(*s)
+            ));
+            lines.push(format!("{} -> {};", id, elem));
+            for &(ref port, ref x) in [("p", *p), ("s", *s)].iter() {
+                if !x.is_const() {
+                    let from = format!("{}:{}", elem, port);
+                    lines.push(edge(&from, &sdd_id(*x), false, sdd_is_compl(*x)));
+                    helper(man, *x, names, seen, clusters, lines);
+                }
+            }
+        }
+    }
+
+    let names = names.or(Some(man.names().as_map()));
+    let mut lines = Vec::new();
+    let mut clusters = BTreeMap::new();
+    let mut seen = HashSet::new();
+    for &(_, ref ptr) in roots.iter() {
+        helper(man, *ptr, names, &mut seen, &mut clusters, &mut lines);
+    }
+
+    let mut r = String::from("digraph sdd {\n  node [shape=circle];\n");
+    if roots.iter().any(|&(_, ref p)| p.is_const()) {
+        r.push_str("  t [label=\"T\", shape=box];\n");
+    }
+    for l in lines.iter() {
+        r.push_str(&format!("  {}\n", l));
+    }
+    for (vnode, c) in clusters.iter() {
+        let prefix = format!("b{}_", vnode);
+        r.push_str(&format!("  subgraph cluster_{} {{\n", vnode));
+        r.push_str(&format!("    label=\"vtree {}\";\n", vnode));
+        r.push_str(&format!(
+            "    {} [label=\"T\", shape=box];\n",
+            bdd_id(&prefix, BddPtr::true_node())
+        ));
+        for l in c.lines.iter() {
+            r.push_str(&format!("    {}\n", l));
+        }
+        for (_, ids) in c.levels.iter() {
+            r.push_str(&format!("    {{ rank=same; {}; }}\n", ids.join("; ")));
+        }
+        r.push_str("  }\n");
+    }
+    for (i, &(ref name, ref ptr)) in roots.iter().enumerate() {
+        r.push_str(&format!("  r{} [label=\"{}\", shape=plaintext];\n", i, escape(name)));
+        r.push_str(&format!(
+            "  {}\n",
+            edge(&format!("r{}", i), &sdd_id(*ptr), false, sdd_is_compl(*ptr))
+        ));
+    }
+    r.push_str("}\n");
+    r
+}
+
+/// Draws `vtree` as a DOT graph, labeling each node with its depth-first index
+/// (the index returned by `SddPtr::vtree()`) and each leaf with its variables
+pub fn vtree_to_dot(vtree: &VTree, names: Option<&HashMap<VarLabel, String>>) -> String {
+    /// draws the subtree `t`, whose first node has index `next`; returns the
+    /// index of its root and the next unused index
+    fn helper(
+        t: &VTree,
+        next: usize,
+        names: Option<&HashMap<VarLabel, String>>,
+        lines: &mut Vec<String>,
+    ) -> (usize, usize) {
+        match t {
+            &BTree::Leaf(ref vars) => {
+                let vars: Vec<String> = vars.iter().map(|v| var_name(names, *v)).collect();
+                lines.push(format!(
+                    "v{} [label=\"{}: {}\", shape=box];",
+                    next,
+                    next,
+                    vars.join(", ")
+                ));
+                (next, next + 1)
+            }
+            &BTree::Node(_, ref l, ref r) => {
+                let (l_root, id) = helper(l, next, names, lines);
+                let (r_root, end) = helper(r, id + 1, names, lines);
+                lines.push(format!("v{} [label=\"{}\"];", id, id));
+                lines.push(format!("v{} -> v{};", id, l_root));
+                lines.push(format!("v{} -> v{};", id, r_root));
+                (id, end)
+            }
+        }
+    }
+    let mut lines = Vec::new();
+    helper(vtree, 0, names, &mut lines);
+    let mut r = String::from("digraph vtree {\n  node [shape=circle];\n");
+    for l in lines.iter() {
+        r.push_str(&format!("  {}\n", l));
+    }
+    r.push_str("}\n");
+    r
+}
+
+#[cfg(test)]
+mod test_dot {
+    use super::*;
+    use manager::var_order::VarOrder;
+
+    #[test]
+    fn bdd_dot_shares_nodes() {
+        let order = VarOrder::new(vec![VarLabel::new(1), VarLabel::new(0), VarLabel::new(2)]);
+        let mut man = BddManager::new(order);
+        let a = man.var(VarLabel::new(0), true);
+        let b = man.var(VarLabel::new(1), true);
+        let c = man.var(VarLabel::new(2), true);
+        let ab = man.and(a, b);
+        let f = man.or(ab, c);
+        let g = man.and(b, c);
+        let mut names = HashMap::new();
+        names.insert(VarLabel::new(0), String::from("a"));
+        names.insert(VarLabel::new(1), String::from("\"b\""));
+        let roots = vec![(String::from("f"), f), (String::from("not g"), g.neg())];
+        let dot = bdd_to_dot(&man, &roots, Some(&names));
+
+        assert!(dot.starts_with("digraph bdd {"));
+        // each distinct node is drawn exactly once
+        let mut seen = HashSet::new();
+        let mut count = 0;
+        for r in [f, g].iter() {
+            fn collect(man: &BddManager, p: BddPtr, s: &mut HashSet<BddPtr>) {
+                if !p.is_const() && s.insert(p.regular()) {
+                    collect(man, man.low(p), s);
+                    collect(man, man.high(p), s);
+                }
+            }
+            collect(&man, *r, &mut seen);
+        }
+        for l in dot.lines() {
+            if l.contains("[label=") && !l.contains("shape") {
+                count += 1;
+            }
+        }
+        assert_eq!(count, seen.len());
+        // the root of `not g` is complemented, and names are escaped
+        assert!(dot.contains("r1 -> ") && dot.contains("arrowhead=odot"));
+        assert!(dot.contains("label=\"\\\"b\\\"\""));
+        assert!(dot.contains("label=\"a\""));
+        assert!(dot.contains("label=\"2\""));
+        // the levels follow the variable order: `b` is ranked first
+        let first = dot.lines().find(|l| l.contains("rank=same")).unwrap();
+        let ids = first.trim().trim_start_matches("{ rank=same;").trim_end_matches("; }");
+        for id in ids.split("; ") {
+            let def = format!("{} [label=\"\\\"b\\\"\"];", id.trim());
+            assert!(dot.contains(&def));
+        }
+    }
+
+    #[test]
+    fn sdd_dot_draws_each_node_once() {
+        use manager::sdd_manager::even_split;
+        use repr::cnf::Cnf;
+        let cnf = Cnf::from_file(String::from("p cnf 6 3\n1 -4 0\n2 5 -6 0\n-3 6 0\n"));
+        let order: Vec<VarLabel> = (0..6).map(|x| VarLabel::new(x)).collect();
+        let mut man = SddManager::new(even_split(&order, 2));
+        let f = man.from_cnf(&cnf);
+        let roots = vec![(String::from("f"), f), (String::from("not f"), f.neg())];
+        let dot = sdd_to_dot(&man, &roots, None);
+        assert!(dot.starts_with("digraph sdd {"));
+        assert!(dot.contains("subgraph cluster_0"));
+
+        fn collect(man: &SddManager, p: SddPtr, s: &mut HashSet<SddPtr>) {
+            if p.is_const() || p.is_bdd() {
+                return;
+            }
+            let reg = if p.is_compl() { p.neg() } else { p };
+            if s.insert(reg) {
+                for &(ref a, ref b) in man.elements(reg).iter() {
+                    collect(man, *a, s);
+                    collect(man, *b, s);
+                }
+            }
+        }
+        let mut nodes = HashSet::new();
+        collect(&man, f, &mut nodes);
+        let drawn = dot
+            .lines()
+            .map(|l| l.trim_start())
+            .filter(|l| l.starts_with("d") && l.contains("[label=") && !l.contains("record"))
+            .count();
+        assert_eq!(drawn, nodes.len());
+        assert!(dot.contains("r1 -> ") && dot.contains("arrowhead=odot"));
+    }
+
+    #[test]
+    fn vtree_dot_indices() {
+        use manager::sdd_manager::even_split;
+        let order: Vec<VarLabel> = (0..4).map(|x| VarLabel::new(x)).collect();
+        let vtree = even_split(&order, 2);
+        let mut names = HashMap::new();
+        names.insert(VarLabel::new(3), String::from("d"));
+        let dot = vtree_to_dot(&vtree, Some(&names));
+        // the leaves are numbered by a depth-first, left-first traversal
+        assert!(dot.contains("v0 [label=\"0: 0\", shape=box];"));
+        assert!(dot.contains("v1 [label=\"1\"];"));
+        assert!(dot.contains("v6 [label=\"6: d\", shape=box];"));
+        assert!(dot.contains("v3 -> v1;") && dot.contains("v3 -> v5;"));
+    }
+}
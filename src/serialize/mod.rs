@@ -0,0 +1,89 @@
+//! Reading and writing decision diagrams to and from files
+
+use repr::var_label::{VarLabel, VarNames, MAX_VAR_SIZE};
+use std::collections::HashMap;
+use std::fmt;
+
+pub mod bdd_binary;
+pub mod dddmp;
+pub mod dot;
+pub mod nnf;
+pub mod ucla;
+
+/// An error encountered while parsing a text format
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// the (1-based) line on which the error occurred
+    pub line: usize,
+    pub msg: String,
+}
+
+impl ParseError {
+    pub fn new(line: usize, msg: &str) -> ParseError {
+        ParseError {
+            line: line,
+            msg: String::from(msg),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.msg)
+    }
+}
+
+/// Writes `names` as comment lines `c name <var> <name>` for the UCLA and c2d
+/// formats, with variables numbered from 1
+pub fn write_names(names: &VarNames) -> String {
+    let mut named: Vec<(&VarLabel, &String)> = names.as_map().iter().collect();
+    named.sort_by_key(|&(v, _)| v.value());
+    let mut r = String::new();
+    for (v, name) in named.into_iter() {
+        r.push_str(&format!("c name {} {}\n", v.value() + 1, name));
+    }
+    r
+}
+
+/// Reads the names written by `write_names` from the comment lines of `s`,
+/// whose variables must be at most `num_vars`; other comments are ignored
+pub fn read_names(s: &str, num_vars: usize) -> Result<HashMap<VarLabel, String>, ParseError> {
+    let mut names: HashMap<VarLabel, String> = HashMap::new();
+    for (i, l) in s.lines().enumerate() {
+        let toks: Vec<&str> = l.split_whitespace().collect();
+        let var: usize = match toks.get(2).and_then(|t| t.parse().ok()) {
+            Some(v) if toks[0] == "c" && toks[1] == "name" => v,
+            _ => continue,
+        };
+        let line = i + 1;
+        if var == 0 || var > MAX_VAR_SIZE.min(num_vars) {
+            return Err(ParseError::new(line, "named variable out of range"));
+        }
+        if toks.len() != 4 {
+            return Err(ParseError::new(line, "expected a single name"));
+        }
+        let name = String::from(toks[3]);
+        let lbl = VarLabel::new(var as u64 - 1);
+        if names.contains_key(&lbl) || names.values().any(|n| *n == name) {
+            return Err(ParseError::new(line, "variable or name is named twice"));
+        }
+        names.insert(lbl, name);
+    }
+    Ok(names)
+}
+
+#[test]
+fn names_roundtrip() {
+    let mut names = VarNames::new();
+    names.insert(VarLabel::new(0), "a");
+    names.insert(VarLabel::new(2), "c");
+    let s = write_names(&names);
+    assert_eq!(s, "c name 1 a\nc name 3 c\n");
+    let read = read_names(&format!("c name of the file\n{}", s), 3).unwrap();
+    assert_eq!(&read, names.as_map());
+    assert_eq!(read_names(&s, 2).unwrap_err().line, 2);
+    assert_eq!(read_names("c name 1 a\nc name 2 a\n", 2).unwrap_err().line, 2);
+    // names which cannot be inserted cannot be read either
+    assert_eq!(read_names("c name 1 a b\n", 2).unwrap_err().line, 1);
+    assert_eq!(read_names("c name 1\n", 2).unwrap_err().line, 1);
+}
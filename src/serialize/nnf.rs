@@ -0,0 +1,360 @@
+//! Export of BDDs and SDDs as deterministic, decomposable NNF circuits
+//! (d-DNNFs), which can be written in the c2d `.nnf` format with
+//! `Nnf::to_c2d`.
+//!
+//! A smooth circuit mentions every variable of the diagram's manager below
+//! each disjunction, so it can be model counted directly; smoothing pads gaps
+//! with `(x || !x)` terms and may grow the circuit.
+
+use manager::rsbdd_manager::BddManager;
+use manager::sdd_manager::SddManager;
+use repr::bdd::BddPtr;
+use repr::nnf::{Nnf, NnfNode};
+use repr::sdd::{SddPtr, VTree};
+use repr::var_label::VarLabel;
+use std::collections::HashMap;
+use util::btree::BTree;
+
+/// Conjoins `child` with a `(x || !x)` term for each variable in `vars`
+fn pad(nnf: &mut Nnf, child: usize, vars: &[VarLabel]) -> usize {
+    if vars.is_empty() {
+        return child;
+    }
+    let mut conj = vec![child];
+    for v in vars.iter() {
+        let pos = nnf.add(NnfNode::Lit(*v, true));
+        let neg = nnf.add(NnfNode::Lit(*v, false));
+        conj.push(nnf.add(NnfNode::Or(Some(*v), vec![pos, neg])));
+    }
+    nnf.add(NnfNode::And(conj))
+}
+
+/// Translates the BDD `ptr`, whose variables are renamed by `label`; when
+/// smoothing, the result mentions every variable of `man` at or below the
+/// position of `ptr` in the order
+fn bdd_h<F>(
+    man: &BddManager,
+    ptr: BddPtr,
+    smooth: bool,
+    label: &F,
+    nnf: &mut Nnf,
+    memo: &mut HashMap<BddPtr, usize>,
+) -> usize
+where
+    F: Fn(VarLabel) -> VarLabel,
+{
+    if ptr.is_true() {
+        return nnf.true_node();
+    } else if ptr.is_false() {
+        return nnf.false_node();
+    }
+    match memo.get(&ptr) {
+        Some(idx) => return *idx,
+        None => (),
+    }
+    let order = man.get_order();
+    let level = |p: BddPtr| {
+        if p.is_const() {
+            order.len()
+        } else {
+            order.get(p.label())
+        }
+    };
+    let reg = ptr.regular();
+    let (low, high) = if ptr.is_compl() {
+        (man.low(reg).neg(), man.high(reg).neg())
+    } else {
+        (man.low(reg), man.high(reg))
+    };
+    let var = label(man.topvar(reg));
+    let mut branches = Vec::new();
+    for &(child, polarity) in [(high, true), (low, false)].iter() {
+        if child.is_false() {
+            continue;
+        }
+        let lit = nnf.add(NnfNode::Lit(var, polarity));
+        let mut conj = vec![lit];
+        if !child.is_true() {
+            conj.push(bdd_h(man, child, smooth, label, nnf, memo));
+        }
+        if smooth {
+            let gap: Vec<VarLabel> = (level(ptr) + 1..level(child))
+                .map(|pos| label(order.var_at_pos(pos)))
+                .collect();
+            let c = nnf.add(NnfNode::And(conj));
+            branches.push(pad(nnf, c, &gap));
+        } else if conj.len() == 1 {
+            branches.push(lit);
+        } else {
+            branches.push(nnf.add(NnfNode::And(conj)));
+        }
+    }
+    let r = nnf.add(NnfNode::Or(Some(var), branches));
+    memo.insert(ptr, r);
+    r
+}
+
+/// Translates the BDD `ptr`; when smoothing, the result mentions every
+/// variable of `man`
+fn bdd_root<F>(
+    man: &BddManager,
+    ptr: BddPtr,
+    smooth: bool,
+    label: &F,
+    nnf: &mut Nnf,
+    memo: &mut HashMap<BddPtr, usize>,
+) -> usize
+where
+    F: Fn(VarLabel) -> VarLabel,
+{
+    let r = bdd_h(man, ptr, smooth, label, nnf, memo);
+    if !smooth || ptr.is_false() {
+        return r;
+    }
+    let order = man.get_order();
+    let top = if ptr.is_const() {
+        order.len()
+    } else {
+        order.get(ptr.label())
+    };
+    let gap: Vec<VarLabel> = (0..top).map(|pos| label(order.var_at_pos(pos))).collect();
+    pad(nnf, r, &gap)
+}
+
+/// Converts the BDD `ptr` into a d-DNNF over the variables of `man`, smoothed
+/// if `smooth` is set; the circuit's variables keep their names in `man`
+pub fn bdd_to_nnf(man: &BddManager, ptr: BddPtr, smooth: bool) -> Nnf {
+    let mut nnf = Nnf::new(man.get_order().len());
+    let r = bdd_root(man, ptr, smooth, &|v| v, &mut nnf, &mut HashMap::new());
+    nnf.set_root(r);
+    *nnf.names_mut() = man.names().clone();
+    nnf
+}
+
+/// For each vtree node (in in-order), the variables below it and the indices
+/// of its children
+fn vtree_info(vtree: &VTree) -> Vec<(Vec<VarLabel>, Option<(usize, usize)>)> {
+    fn helper(t: &VTree, info: &mut Vec<(Vec<VarLabel>, Option<(usize, usize)>)>) -> usize {
+        match t {
+            &BTree::Leaf(ref vars) => {
+                info.push((vars.clone(), None));
+                info.len() - 1
+            }
+            &BTree::Node(_, ref l, ref r) => {
+                let l_idx = helper(l, info);
+                info.push((Vec::new(), None));
+                let idx = info.len() - 1;
+                let r_idx = helper(r, info);
+                let mut vars = info[l_idx].0.clone();
+                vars.extend(info[r_idx].0.iter().cloned());
+                info[idx] = (vars, Some((l_idx, r_idx)));
+                idx
+            }
+        }
+    }
+    let mut info = Vec::new();
+    helper(vtree, &mut info);
+    info
+}
+
+struct SddConverter<'a> {
+    man: &'a SddManager,
+    smooth: bool,
+    info: Vec<(Vec<VarLabel>, Option<(usize, usize)>)>,
+    nnf: Nnf,
+    memo: HashMap<SddPtr, usize>,
+    /// a memo table for the BDD at each vtree leaf
+    bdd_memo: HashMap<usize, HashMap<BddPtr, usize>>,
+}
+
+impl<'a> SddConverter<'a> {
+    /// the variables below the vtree node `vnode` which are not mentioned by
+    /// the translation of `f`
+    fn gap(&self, vnode: usize, f: SddPtr) -> Vec<VarLabel> {
+        if f.is_const() {
+            return self.info[vnode].0.clone();
+        }
+        let covered = &self.info[f.vtree()].0;
+        self.info[vnode]
+            .0
+            .iter()
+            .filter(|v| !covered.contains(v))
+            .cloned()
+            .collect()
+    }
+
+    fn sdd(&mut self, f: SddPtr) -> usize {
+        if f.is_true() {
+            return self.nnf.true_node();
+        } else if f.is_false() {
+            return self.nnf.false_node();
+        }
+        match self.memo.get(&f) {
+            Some(idx) => return *idx,
+            None => (),
+        }
+        let vnode = f.vtree();
+        let r = if f.is_bdd() {
+            let man = self.man;
+            let label = |lbl| man.bdd_to_sdd_label(vnode, lbl);
+            let memo = self.bdd_memo.entry(vnode).or_insert_with(HashMap::new);
+            bdd_root(
+                man.get_bdd_mgr(f),
+                f.as_bdd_ptr(),
+                self.smooth,
+                &label,
+                &mut self.nnf,
+                memo,
+            )
+        } else {
+            let (l, r) = self.info[vnode].1.unwrap();
+            let mut branches = Vec::new();
+            for &(p, s) in self.man.elements(f).iter() {
+                if s.is_false() {
+                    continue;
+                }
+                let p_idx = self.sdd(p);
+                let s_idx = self.sdd(s);
+                let mut conj = vec![p_idx, s_idx];
+                if self.smooth {
+                    let mut gap = self.gap(l, p);
+                    gap.extend(self.gap(r, s));
+                    let c = self.nnf.add(NnfNode::And(conj));
+                    branches.push(pad(&mut self.nnf, c, &gap));
+                } else {
+                    if s.is_true() {
+                        conj.pop();
+                    }
+                    branches.push(if conj.len() == 1 {
+                        conj[0]
+                    } else {
+                        self.nnf.add(NnfNode::And(conj))
+                    });
+                }
+            }
+            // the primes are exclusive, but they need not disagree on a single
+            // variable
+            self.nnf.add(NnfNode::Or(None, branches))
+        };
+        self.memo.insert(f, r);
+        r
+    }
+}
+
+/// Converts the SDD `ptr` into a d-DNNF over the variables of `man`'s vtree,
+/// smoothed if `smooth` is set; the circuit's variables keep their names in
+/// `man`
+pub fn sdd_to_nnf(man: &SddManager, ptr: SddPtr, smooth: bool) -> Nnf {
+    let info = vtree_info(man.get_vtree_root());
+    let num_vars = info
+        .iter()
+        .flat_map(|&(ref vars, _)| vars.iter())
+        .map(|v| v.value() as usize + 1)
+        .max()
+        .unwrap_or(0);
+    // the root of the vtree follows its left subtree in the in-order
+    let root_vnode = match man.get_vtree_root() {
+        &BTree::Leaf(_) => 0,
+        &BTree::Node(_, ref l, _) => l.num_nodes(),
+    };
+    let mut c = SddConverter {
+        man: man,
+        smooth: smooth,
+        info: info,
+        nnf: Nnf::new(num_vars),
+        memo: HashMap::new(),
+        bdd_memo: HashMap::new(),
+    };
+    let mut r = c.sdd(ptr);
+    if smooth && !ptr.is_false() {
+        let gap = c.gap(root_vnode, ptr);
+        r = pad(&mut c.nnf, r, &gap);
+    }
+    c.nnf.set_root(r);
+    *c.nnf.names_mut() = man.names().clone();
+    c.nnf
+}
+
+#[cfg(test)]
+mod test_nnf {
+    use super::*;
+    use manager::rsbdd_manager::BddWmc;
+    use manager::sdd_manager::even_split;
+    use repr::cnf::Cnf;
+
+    fn weights() -> HashMap<VarLabel, (u64, u64)> {
+        (0..10).map(|v| (VarLabel::new(v), (2, v + 3))).collect()
+    }
+
+    /// the assignment to 10 variables given by the bits of `bits`
+    fn assignment(bits: u16) -> HashMap<VarLabel, bool> {
+        (0..10).map(|v| (VarLabel::new(v), bits & (1 << v) != 0)).collect()
+    }
+
+    quickcheck! {
+        fn bdd_nnf_agrees(c: Cnf, bits: u16) -> bool {
+            let mut man = BddManager::new_default_order(10);
+            let f = man.from_cnf(&c);
+            let expected = man.wmc(f, &BddWmc::new_with_default(0, 1, weights()));
+
+            let smooth = bdd_to_nnf(&man, f, true);
+            let read = Nnf::from_c2d(&smooth.to_c2d()).unwrap();
+            let plain = bdd_to_nnf(&man, f, false);
+            let assgn = assignment(bits);
+            smooth.wmc(&weights()) == expected
+                && read.wmc(&weights()) == expected
+                && read.to_c2d() == smooth.to_c2d()
+                && plain.eval(&assgn) == man.eval_bdd(f, &assgn)
+                && smooth.eval(&assgn) == man.eval_bdd(f, &assgn)
+                && plain.is_sat() == !f.is_false()
+        }
+    }
+
+    quickcheck! {
+        fn sdd_nnf_agrees(c: Cnf, bits: u16) -> bool {
+            let mut bdd_man = BddManager::new_default_order(10);
+            let bdd = bdd_man.from_cnf(&c);
+            let expected = bdd_man.wmc(bdd, &BddWmc::new_with_default(0, 1, weights()));
+
+            let order: Vec<VarLabel> = (0..10).map(|x| VarLabel::new(x)).collect();
+            let mut man = SddManager::new(even_split(&order, 3));
+            let f = man.from_cnf(&c);
+            let smooth = sdd_to_nnf(&man, f, true);
+            let read = Nnf::from_c2d(&smooth.to_c2d()).unwrap();
+            let plain = sdd_to_nnf(&man, f, false);
+            let assgn = assignment(bits);
+            smooth.wmc(&weights()) == expected
+                && read.wmc(&weights()) == expected
+                && plain.eval(&assgn) == man.eval_sdd(f, &assgn)
+                && smooth.eval(&assgn) == man.eval_sdd(f, &assgn)
+                && plain.is_sat() == !man.is_false(f)
+        }
+    }
+
+    #[test]
+    fn constants() {
+        let man = BddManager::new_default_order(2);
+        assert_eq!(bdd_to_nnf(&man, man.false_ptr(), true).to_c2d(), "nnf 1 0 2\nO 0 0\n");
+        assert_eq!(bdd_to_nnf(&man, man.true_ptr(), false).to_c2d(), "nnf 1 0 2\nA 0\n");
+        let smooth = bdd_to_nnf(&man, man.true_ptr(), true);
+        let w: HashMap<VarLabel, (u64, u64)> =
+            (0..2).map(|v| (VarLabel::new(v), (1, 1))).collect();
+        assert_eq!(smooth.wmc(&w), 4);
+    }
+
+    #[test]
+    fn names_roundtrip() {
+        let order: Vec<VarLabel> = (0..4).map(|x| VarLabel::new(x)).collect();
+        let mut man = SddManager::new(even_split(&order, 1));
+        man.names_mut().insert(VarLabel::new(1), "a");
+        man.names_mut().insert(VarLabel::new(3), "b");
+        let f = man.parse_expr("a & !b").unwrap();
+        let nnf = sdd_to_nnf(&man, f, false);
+        assert_eq!(nnf.names(), man.names());
+        let s = nnf.to_c2d();
+        assert!(s.starts_with("c name 2 a\nc name 4 b\nnnf "));
+        let read = Nnf::from_c2d(&s).unwrap();
+        assert_eq!(read.names(), man.names());
+        assert_eq!(read.to_c2d(), s);
+    }
+}
@@ -142,6 +142,90 @@ where
         }
         v
     }
+
+    /// the number of nodes and leaves in the tree
+    pub fn num_nodes(&self) -> usize {
+        match self {
+            &BTree::Leaf(_) => 1,
+            &BTree::Node(_, ref l, ref r) => l.num_nodes() + r.num_nodes() + 1,
+        }
+    }
+
+    /// Applies `f` to the subtree rooted at in-order index `idx`, returning the
+    /// new tree; None if `f` fails or `idx` is out of range
+    fn transform_at<F>(&self, idx: usize, f: &F) -> Option<BTree<N, L>>
+    where
+        F: Fn(&BTree<N, L>) -> Option<BTree<N, L>>,
+    {
+        match self {
+            &BTree::Leaf(_) => {
+                if idx == 0 {
+                    f(self)
+                } else {
+                    None
+                }
+            }
+            &BTree::Node(ref v, ref l, ref r) => {
+                let l_sz = l.num_nodes();
+                if idx < l_sz {
+                    let new_l = l.transform_at(idx, f)?;
+                    Some(BTree::Node(v.clone(), Box::new(new_l), r.clone()))
+                } else if idx == l_sz {
+                    f(self)
+                } else {
+                    let new_r = r.transform_at(idx - l_sz - 1, f)?;
+                    Some(BTree::Node(v.clone(), l.clone(), Box::new(new_r)))
+                }
+            }
+        }
+    }
+
+    /// Rotates the node at in-order index `idx` to the left, so that
+    /// `(a, (b, c))` becomes `((a, b), c)`; None if its right child is a leaf.
+    /// Node data stays at the same in-order positions, so the in-order index
+    /// of every node is unchanged.
+    pub fn rotate_left(&self, idx: usize) -> Option<BTree<N, L>> {
+        self.transform_at(idx, &|t| match t {
+            &BTree::Node(ref x, ref a, ref r) => match **r {
+                BTree::Node(ref y, ref b, ref c) => Some(BTree::Node(
+                    y.clone(),
+                    Box::new(BTree::Node(x.clone(), a.clone(), b.clone())),
+                    c.clone(),
+                )),
+                _ => None,
+            },
+            _ => None,
+        })
+    }
+
+    /// Rotates the node at in-order index `idx` to the right, so that
+    /// `((a, b), c)` becomes `(a, (b, c))`; None if its left child is a leaf.
+    /// Node data stays at the same in-order positions, so the in-order index
+    /// of every node is unchanged.
+    pub fn rotate_right(&self, idx: usize) -> Option<BTree<N, L>> {
+        self.transform_at(idx, &|t| match t {
+            &BTree::Node(ref x, ref l, ref c) => match **l {
+                BTree::Node(ref w, ref a, ref b) => Some(BTree::Node(
+                    w.clone(),
+                    a.clone(),
+                    Box::new(BTree::Node(x.clone(), b.clone(), c.clone())),
+                )),
+                _ => None,
+            },
+            _ => None,
+        })
+    }
+
+    /// Swaps the children of the node at in-order index `idx`; None if it is a
+    /// leaf
+    pub fn swap(&self, idx: usize) -> Option<BTree<N, L>> {
+        self.transform_at(idx, &|t| match t {
+            &BTree::Node(ref x, ref l, ref r) => {
+                Some(BTree::Node(x.clone(), r.clone(), l.clone()))
+            }
+            _ => None,
+        })
+    }
 }
 
 #[test]
@@ -180,3 +264,42 @@ fn test_in_order_tree() {
         assert_eq!(idx, value);
     }
 }
+
+#[test]
+fn test_rotations() {
+    use self::BTree::*;
+    let tree: BTree<i32, i32> = Node(
+        4,
+        Box::new(Node(2, Box::new(Leaf(1)), Box::new(Leaf(3)))),
+        Box::new(Node(6, Box::new(Leaf(5)), Box::new(Leaf(7)))),
+    );
+    let order = |t: &BTree<i32, i32>| -> Vec<i32> {
+        t.in_order_iter()
+            .map(|v| match v {
+                &Node(ref v, _, _) => v.clone(),
+                &Leaf(ref v) => v.clone(),
+            })
+            .collect()
+    };
+    // rotations preserve the in-order sequence and change the root
+    let rl = tree.rotate_left(3).unwrap();
+    assert_eq!(order(&rl), order(&tree));
+    match rl {
+        Node(ref v, _, ref r) => {
+            assert_eq!(*v, 6);
+            assert_eq!(*r.extract_leaf(), 7);
+        }
+        _ => panic!("expected a node"),
+    }
+    let rr = rl.rotate_right(5).unwrap();
+    assert_eq!(order(&rr), order(&tree));
+    match rr {
+        Node(ref v, _, _) => assert_eq!(*v, 4),
+        _ => panic!("expected a node"),
+    }
+    assert!(tree.rotate_left(1).is_none());
+    assert!(tree.rotate_right(0).is_none());
+    // swapping mirrors the subtree
+    let sw = tree.swap(1).unwrap();
+    assert_eq!(order(&sw), vec![3, 2, 1, 4, 5, 6, 7]);
+}